@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    GameplaySet, GoalEvent, InGame, MissEvent, ScoreEvent, in_play,
+    goal::{Home, choose_home},
+    grid::GridPos,
+    player::{Dying, Hop, Player, PlayerAtlas},
+    replay::GameRng,
+    river::Log,
+};
+
+/// ハエが現れる間隔（秒）
+const FLY_INTERVAL_SECS: f32 = 7.0;
+/// ハエが巣にいる時間（秒）
+const FLY_SECS: f32 = 4.0;
+const FLY_SIZE: Vec2 = Vec2::splat(10.0);
+const COLOR_FLY: Color = Color::srgb(0.9, 0.9, 0.9);
+/// メスのカエルが現れる間隔（秒）
+const LADY_FROG_INTERVAL_SECS: f32 = 12.0;
+/// メスのカエルが丸太に乗っている時間（秒）
+const LADY_FROG_SECS: f32 = 8.0;
+/// メスのカエルを拾える、丸太の中心からの横方向の距離
+const LADY_FROG_PICKUP_DISTANCE: f32 = Player::DISTANCE / 2.0;
+/// プレイヤーに連れられているときの位置（プレイヤーから見た位置）
+const LADY_FROG_ESCORT_OFFSET: Vec3 = Vec3::new(0.0, -4.0, 0.1);
+const COLOR_LADY_FROG: Color = Color::srgb(1.0, 0.5, 0.7);
+
+/// ボーナスを出すシステムのセット
+/// 乱数を使うほかのシステムはこのセットの前か後に実行し、乱数を使う順番を毎回同じにする
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BonusSystem;
+
+/// 巣に現れるハエのコンポーネント
+/// - home: いる巣の番号
+/// - timer: いなくなるまでのタイマー
+#[derive(Component, Debug)]
+pub struct Fly {
+    pub home: usize,
+    timer: Timer,
+}
+
+/// 丸太に乗って現れるメスのカエルのコンポーネント
+/// 丸太の子として配置し、丸太と一緒に流れる
+/// - timer: プレイヤーに拾われずにいなくなるまでのタイマー
+#[derive(Component, Debug)]
+struct LadyFrog {
+    timer: Timer,
+}
+
+/// メスのカエルがプレイヤーに連れられていることを表すコンポーネント
+/// 連れられている間はプレイヤーの子にする
+#[derive(Component, Debug)]
+struct Escorted;
+
+/// ボーナスが現れるまでのタイマーを管理するリソース
+#[derive(Resource, Debug)]
+struct BonusTimers {
+    fly: Timer,
+    lady_frog: Timer,
+}
+
+impl Default for BonusTimers {
+    fn default() -> Self {
+        Self {
+            fly: Timer::from_seconds(FLY_INTERVAL_SECS, TimerMode::Repeating),
+            lady_frog: Timer::from_seconds(LADY_FROG_INTERVAL_SECS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// ボーナスのタイマーを用意する関数
+fn bonus_setup(mut commands: Commands) {
+    info_once!("bonus_setup");
+
+    commands.insert_resource(BonusTimers::default());
+}
+
+/// 一定の間隔で、空いている巣のどれかにハエを出す関数
+/// 出ているハエは時間が経ったらいなくなる
+fn bonus_fly(
+    mut commands: Commands,
+    mut timers: ResMut<BonusTimers>,
+    mut rng: ResMut<GameRng>,
+    mut fly_query: Query<(Entity, &mut Fly)>,
+    home_query: Query<(&Home, &Transform)>,
+    time: Res<Time>,
+) {
+    info_once!("bonus_fly");
+
+    let mut active = false;
+    for (entity, mut fly) in &mut fly_query {
+        if fly.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        } else {
+            active = true;
+        }
+    }
+
+    if !timers.fly.tick(time.delta()).just_finished() || active {
+        return;
+    }
+
+    let homes = home_query.iter().filter(|(home, _)| !home.filled && !home.crocodile).collect();
+    let Some((home, transform)) = choose_home(homes, |(home, _)| home, &mut rng) else {
+        return;
+    };
+
+    commands.spawn((
+        Sprite::from_color(COLOR_FLY, FLY_SIZE),
+        Transform::from_translation(transform.translation.with_z(0.0)),
+        Fly {
+            home: home.index,
+            timer: Timer::from_seconds(FLY_SECS, TimerMode::Once),
+        },
+        StateScoped(InGame),
+    ));
+}
+
+/// 一定の間隔で、丸太のどれかにメスのカエルを乗せる関数
+/// 拾われなかったメスのカエルは時間が経ったらいなくなる
+fn bonus_lady_frog(
+    mut commands: Commands,
+    mut timers: ResMut<BonusTimers>,
+    mut rng: ResMut<GameRng>,
+    mut lady_frog_query: Query<(Entity, &mut LadyFrog, Has<Escorted>)>,
+    log_query: Query<(Entity, &Transform, &GridPos), With<Log>>,
+    atlas: Res<PlayerAtlas>,
+    time: Res<Time>,
+) {
+    info_once!("bonus_lady_frog");
+
+    let mut active = false;
+    for (entity, mut lady_frog, escorted) in &mut lady_frog_query {
+        if !escorted && lady_frog.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            active = true;
+        }
+    }
+
+    if !timers.lady_frog.tick(time.delta()).just_finished() || active {
+        return;
+    }
+
+    // 乱数の結果がクエリの順番に左右されないよう、位置の順に並べてから選ぶ
+    let mut logs: Vec<_> = log_query.iter().collect();
+    if logs.is_empty() {
+        return;
+    }
+    logs.sort_by(|(_, a, a_grid_pos), (_, b, b_grid_pos)| {
+        a_grid_pos.row.cmp(&b_grid_pos.row).then(a.translation.x.total_cmp(&b.translation.x))
+    });
+    let (log, _, _) = logs[rng.gen_range(0..logs.len())];
+
+    commands.entity(log).with_child((
+        Sprite {
+            color: COLOR_LADY_FROG,
+            ..atlas.sprite(Player::INDICES_TOP.0)
+        },
+        Transform::from_xyz(0.0, 0.0, 0.5).with_scale(Vec3::splat(2.0)),
+        LadyFrog {
+            timer: Timer::from_seconds(LADY_FROG_SECS, TimerMode::Once),
+        },
+    ));
+}
+
+/// プレイヤーがメスのカエルの乗っている丸太の中心に着地したら、連れていく関数
+#[allow(clippy::type_complexity)]
+fn bonus_lady_frog_pickup(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Transform, &GridPos), (With<Player>, Without<Hop>, Without<Dying>)>,
+    lady_frog_query: Query<(Entity, &Parent), (With<LadyFrog>, Without<Escorted>)>,
+    log_query: Query<(&Transform, &GridPos), With<Log>>,
+) {
+    info_once!("bonus_lady_frog_pickup");
+
+    let Ok((player, transform, grid_pos)) = player_query.get_single() else {
+        return;
+    };
+
+    for (entity, parent) in &lady_frog_query {
+        let Ok((log_transform, log_grid_pos)) = log_query.get(parent.get()) else {
+            continue;
+        };
+        if log_grid_pos.row != grid_pos.row
+            || (transform.translation.x - log_transform.translation.x).abs() > LADY_FROG_PICKUP_DISTANCE
+        {
+            continue;
+        }
+
+        commands
+            .entity(entity)
+            .set_parent(player)
+            .insert((Escorted, Transform::from_translation(LADY_FROG_ESCORT_OFFSET)));
+    }
+}
+
+/// 巣に入ったらハエとメスのカエルのボーナス点を知らせる関数
+/// ミスしたら連れていたメスのカエルはいなくなる
+fn bonus_catch(
+    mut commands: Commands,
+    mut goal_events: EventReader<GoalEvent>,
+    mut miss_events: EventReader<MissEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    fly_query: Query<(Entity, &Fly)>,
+    escorted_query: Query<Entity, With<Escorted>>,
+) {
+    info_once!("bonus_catch");
+
+    for goal in goal_events.read() {
+        for (entity, fly) in &fly_query {
+            if fly.home == **goal {
+                score_events.send(ScoreEvent::Fly);
+                commands.entity(entity).despawn();
+            }
+        }
+        for entity in &escorted_query {
+            score_events.send(ScoreEvent::LadyFrog);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if !miss_events.is_empty() {
+        miss_events.clear();
+
+        for entity in &escorted_query {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub struct BonusPlugin;
+
+impl Plugin for BonusPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BonusTimers>()
+            .add_systems(OnEnter(InGame), bonus_setup)
+            .add_systems(FixedUpdate, (
+                bonus_fly,
+                bonus_lady_frog,
+                bonus_lady_frog_pickup,
+            ).chain().in_set(GameplaySet::Collision).in_set(BonusSystem).run_if(in_play))
+            // ゴールのイベントは、送られた固定タイムステップのうちにボーナス点にする
+            .add_systems(FixedPostUpdate, bonus_catch.run_if(in_state(InGame)))
+        ;
+    }
+}
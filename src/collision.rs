@@ -0,0 +1,507 @@
+use bevy::{
+    prelude::*,
+    ecs::entity::EntityHashMap,
+    utils::HashMap,
+};
+
+use crate::{
+    GameplaySet, InGame, in_play,
+    grid::{Grid, GridPos},
+    player::Player,
+};
+
+/// 当たり判定の表示を切り替えるキー
+const KEY_DEBUG: KeyCode = KeyCode::F3;
+const COLOR_DEBUG_PLAYER: Color = Color::srgb(1.0, 1.0, 1.0);
+const COLOR_DEBUG_VEHICLE: Color = Color::srgb(1.0, 0.2, 0.2);
+const COLOR_DEBUG_ENEMY: Color = Color::srgb(1.0, 0.2, 1.0);
+const COLOR_DEBUG_PLATFORM: Color = Color::srgb(0.2, 1.0, 0.2);
+const COLOR_DEBUG_WATER: Color = Color::srgb(0.2, 0.4, 1.0);
+const COLOR_DEBUG_HOME: Color = Color::srgb(1.0, 1.0, 0.2);
+
+/// 当たり判定の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitboxKind {
+    Player,
+    Vehicle,
+    Enemy,
+    Platform,
+    Water,
+    Home,
+}
+
+impl HitboxKind {
+    fn debug_color(&self) -> Color {
+        match self {
+            HitboxKind::Player => COLOR_DEBUG_PLAYER,
+            HitboxKind::Vehicle => COLOR_DEBUG_VEHICLE,
+            HitboxKind::Enemy => COLOR_DEBUG_ENEMY,
+            HitboxKind::Platform => COLOR_DEBUG_PLATFORM,
+            HitboxKind::Water => COLOR_DEBUG_WATER,
+            HitboxKind::Home => COLOR_DEBUG_HOME,
+        }
+    }
+}
+
+/// 軸に沿った長方形の当たり判定のコンポーネント
+/// スプライトの拡大率とは関係なく、ワールド座標の大きさで指定する
+/// - kind: 当たり判定の種類
+/// - size: 大きさ
+/// - offset: Transformの位置からのずれ
+/// - enabled: 判定するか。潜っている亀などは無効にする
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Hitbox {
+    pub kind: HitboxKind,
+    pub size: Vec2,
+    pub offset: Vec2,
+    pub enabled: bool,
+}
+
+impl Hitbox {
+    pub fn new(kind: HitboxKind, size: Vec2) -> Self {
+        Self {
+            kind,
+            size,
+            offset: Vec2::ZERO,
+            enabled: true,
+        }
+    }
+
+    /// 位置から当たり判定の長方形を求める
+    fn rect(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+/// プレイヤーが何かに当たったことを知らせるイベント
+/// どれもプレイヤーについてのイベントなので、名前をPlayerで揃える
+#[allow(clippy::enum_variant_names)]
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionEvent {
+    /// 車両に当たった
+    PlayerHitVehicle(Entity),
+    /// 敵の当たるとミスになる部分に当たった
+    PlayerHitEnemy(Entity),
+    /// 川の足場に乗っている
+    PlayerOnPlatform(Entity),
+    /// 足場のない水面にいる
+    PlayerInWater,
+    /// 巣に重なっている
+    PlayerAtHome(Entity),
+}
+
+/// 当たり判定を持つものを行ごとにまとめた索引のリソース
+/// 升目の位置を持つものだけを入れ、子の当たり判定は親と同じ行で調べる
+/// 当たり判定は1行の高さに収まるものとする
+/// - rows: 行ごとのエンティティ
+/// - entries: エンティティが入っている行
+#[derive(Resource, Debug, Default)]
+pub struct RowIndex {
+    rows: HashMap<i32, Vec<Entity>>,
+    entries: EntityHashMap<i32>,
+}
+
+impl RowIndex {
+    /// 行にあるエンティティを返す
+    pub fn get(&self, row: i32) -> &[Entity] {
+        self.rows.get(&row).map_or(&[], Vec::as_slice)
+    }
+
+    /// エンティティを行に入れる。別の行に入っていたらそこから移す
+    fn insert(&mut self, entity: Entity, row: i32) {
+        match self.entries.insert(entity, row) {
+            Some(old) if old == row => return,
+            Some(old) => self.remove_from_row(entity, old),
+            None => {}
+        }
+        self.rows.entry(row).or_default().push(entity);
+    }
+
+    /// エンティティを索引から取り除く
+    fn remove(&mut self, entity: Entity) {
+        if let Some(row) = self.entries.remove(&entity) {
+            self.remove_from_row(entity, row);
+        }
+    }
+
+    fn remove_from_row(&mut self, entity: Entity, row: i32) {
+        if let Some(entities) = self.rows.get_mut(&row) {
+            entities.retain(|other| *other != entity);
+        }
+    }
+}
+
+/// 当たり判定を表示するかを管理するリソース
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct HitboxDebug(pub bool);
+
+/// 升目の位置が変わった、または当たり判定が付いたものを索引に入れ直す関数
+/// 行が変わっていなければ何もしない
+#[allow(clippy::type_complexity)]
+pub fn collision_index(
+    mut index: ResMut<RowIndex>,
+    query: Query<(Entity, &GridPos), (With<Hitbox>, Without<Player>, Or<(Changed<GridPos>, Added<Hitbox>)>)>,
+) {
+    info_once!("collision_index");
+
+    for (entity, grid_pos) in &query {
+        index.insert(entity, grid_pos.row);
+    }
+}
+
+/// 当たり判定が外れた、または消えたものを索引から取り除く関数
+/// 取りこぼさないよう、オブザーバーとしてすぐに実行する
+fn collision_unindex(trigger: Trigger<OnRemove, Hitbox>, mut index: ResMut<RowIndex>) {
+    info_once!("collision_unindex");
+
+    index.remove(trigger.entity());
+}
+
+/// プレイヤーと重なっている当たり判定を調べ、種類ごとのイベントを送る関数
+/// プレイヤーの当たり判定にかかる行の索引だけを調べる
+/// 水面に重なっていても足場に乗っていれば、水面のイベントは送らない
+pub fn collision_broadphase(
+    mut events: EventWriter<CollisionEvent>,
+    player_query: Query<(&Transform, &Hitbox), With<Player>>,
+    hitbox_query: Query<(&Transform, &Hitbox, Option<&Children>), Without<Player>>,
+    index: Res<RowIndex>,
+    grid: Res<Grid>,
+) {
+    info_once!("collision_broadphase");
+
+    let Ok((player_transform, player_hitbox)) = player_query.get_single() else {
+        return;
+    };
+    let player_rect = player_hitbox.rect(player_transform.translation.truncate());
+    // 跳んでいる途中は2つの行にかかる
+    let rows = grid.to_grid(player_rect.min).row..=grid.to_grid(player_rect.max).row;
+
+    // 索引にあるものと、その子の当たり判定を位置と一緒に並べる
+    let hitboxes = rows
+        .flat_map(|row| index.get(row))
+        .filter_map(|entity| hitbox_query.get(*entity).ok().map(|item| (*entity, item)))
+        .flat_map(|(entity, (transform, hitbox, children))| {
+            let position = transform.translation.truncate();
+            let children = children
+                .into_iter()
+                .flatten()
+                .filter_map(|child| hitbox_query.get(*child).ok().map(|item| (*child, item)))
+                .map(move |(child, (transform, hitbox, _))| (child, position + transform.translation.truncate(), hitbox));
+
+            std::iter::once((entity, position, hitbox)).chain(children)
+        });
+
+    let mut in_water = false;
+    let mut on_platform = false;
+
+    for (entity, position, hitbox) in hitboxes {
+        if !hitbox.enabled {
+            continue;
+        }
+        let rect = hitbox.rect(position);
+        if rect.intersect(player_rect).is_empty() {
+            continue;
+        }
+
+        match hitbox.kind {
+            HitboxKind::Vehicle => {
+                events.send(CollisionEvent::PlayerHitVehicle(entity));
+            }
+            HitboxKind::Enemy => {
+                events.send(CollisionEvent::PlayerHitEnemy(entity));
+            }
+            HitboxKind::Platform => {
+                on_platform = true;
+                events.send(CollisionEvent::PlayerOnPlatform(entity));
+            }
+            HitboxKind::Water => in_water = true,
+            HitboxKind::Home => {
+                events.send(CollisionEvent::PlayerAtHome(entity));
+            }
+            HitboxKind::Player => {}
+        }
+    }
+
+    if in_water && !on_platform {
+        events.send(CollisionEvent::PlayerInWater);
+    }
+}
+
+/// キーで当たり判定の表示を切り替える関数
+fn collision_debug_toggle(
+    mut debug: ResMut<HitboxDebug>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    info_once!("collision_debug_toggle");
+
+    if keyboard_input.just_pressed(KEY_DEBUG) {
+        **debug = !**debug;
+    }
+}
+
+/// 当たり判定の長方形を描く関数
+/// 描画に合わせるため、補間した後の位置を使う
+fn collision_debug_draw(
+    mut gizmos: Gizmos,
+    query: Query<(&GlobalTransform, &Hitbox)>,
+) {
+    info_once!("collision_debug_draw");
+
+    for (transform, hitbox) in &query {
+        if !hitbox.enabled {
+            continue;
+        }
+        let position = transform.translation().truncate() + hitbox.offset;
+        gizmos.rect_2d(Isometry2d::from_translation(position), hitbox.size, hitbox.kind.debug_color());
+    }
+}
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<HitboxDebug>()
+            .init_resource::<RowIndex>()
+            .add_event::<CollisionEvent>()
+            .add_observer(collision_unindex)
+            .add_systems(FixedUpdate, (
+                collision_index,
+                collision_broadphase,
+            ).chain().in_set(GameplaySet::Collision).run_if(in_play))
+            .add_systems(Update, collision_debug_toggle.run_if(in_state(InGame)))
+            .add_systems(PostUpdate, collision_debug_draw
+                .after(TransformSystem::TransformPropagate)
+                .run_if(in_state(InGame).and(|debug: Res<HitboxDebug>| **debug)))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const ROW: i32 = 5;
+
+    fn app() -> App {
+        let mut app = testing::app();
+        app
+            .init_resource::<Grid>()
+            .init_resource::<RowIndex>()
+            .add_event::<CollisionEvent>()
+            .add_observer(collision_unindex)
+            .add_systems(Update, (collision_index, collision_broadphase).chain());
+        app
+    }
+
+    /// 升目の中央にプレイヤーを置く
+    fn spawn_player(app: &mut App, pos: GridPos) -> Entity {
+        let position = app.world().resource::<Grid>().to_world(pos);
+        app.world_mut().spawn((
+            Player::new(),
+            Hitbox::new(HitboxKind::Player, Player::SIZE),
+            Transform::from_translation(position.extend(0.0)),
+            pos,
+        )).id()
+    }
+
+    /// 升目の中央から横にずらした位置に当たり判定を置く
+    fn spawn_hitbox(app: &mut App, hitbox: Hitbox, pos: GridPos, dx: f32) -> Entity {
+        let position = app.world().resource::<Grid>().to_world(pos) + Vec2::new(dx, 0.0);
+        app.world_mut().spawn((hitbox, Transform::from_translation(position.extend(0.0)), pos)).id()
+    }
+
+    fn collisions(app: &mut App) -> Vec<CollisionEvent> {
+        app.update();
+        testing::drain(app)
+    }
+
+    /// プレイヤーの右端にちょうど接する横のずれ
+    fn touching_dx(size: Vec2) -> f32 {
+        (Player::SIZE.x + size.x) / 2.0
+    }
+
+    #[test]
+    fn touching_edges_do_not_collide() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        let size = Vec2::new(32.0, 24.0);
+        spawn_player(&mut app, pos);
+        spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, size), pos, touching_dx(size));
+
+        assert!(collisions(&mut app).is_empty());
+    }
+
+    #[test]
+    fn overlapping_edges_collide() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        let size = Vec2::new(32.0, 24.0);
+        spawn_player(&mut app, pos);
+        let vehicle = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, size), pos, touching_dx(size) - 1.0);
+
+        assert_eq!(collisions(&mut app), vec![CollisionEvent::PlayerHitVehicle(vehicle)]);
+    }
+
+    #[test]
+    fn offset_moves_hitbox() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        let size = Vec2::new(32.0, 24.0);
+        spawn_player(&mut app, pos);
+        let hitbox = Hitbox { offset: Vec2::new(-2.0, 0.0), ..Hitbox::new(HitboxKind::Enemy, size) };
+        let enemy = spawn_hitbox(&mut app, hitbox, pos, touching_dx(size) + 1.0);
+
+        assert_eq!(collisions(&mut app), vec![CollisionEvent::PlayerHitEnemy(enemy)]);
+    }
+
+    #[test]
+    fn disabled_hitbox_is_skipped() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        spawn_player(&mut app, pos);
+        let hitbox = Hitbox { enabled: false, ..Hitbox::new(HitboxKind::Platform, Vec2::splat(32.0)) };
+        spawn_hitbox(&mut app, hitbox, pos, 0.0);
+
+        assert!(collisions(&mut app).is_empty());
+    }
+
+    #[test]
+    fn hitbox_in_next_row_does_not_collide() {
+        let mut app = app();
+        spawn_player(&mut app, GridPos::new(5, ROW));
+        spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, Vec2::splat(32.0)), GridPos::new(5, ROW + 1), 0.0);
+
+        assert!(collisions(&mut app).is_empty());
+    }
+
+    #[test]
+    fn player_between_rows_hits_both_rows() {
+        let mut app = app();
+        let player = spawn_player(&mut app, GridPos::new(5, ROW));
+        let size = Vec2::new(32.0, 24.0);
+        let below = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, size), GridPos::new(5, ROW), 0.0);
+        let above = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, size), GridPos::new(5, ROW + 1), 0.0);
+
+        // 跳んでいる途中のように、2つの行の間に置く
+        let cell_size = app.world().resource::<Grid>().cell_size;
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.y += cell_size / 2.0;
+
+        let events = collisions(&mut app);
+        assert!(events.contains(&CollisionEvent::PlayerHitVehicle(below)));
+        assert!(events.contains(&CollisionEvent::PlayerHitVehicle(above)));
+    }
+
+    #[test]
+    fn water_without_platform_drowns() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        spawn_player(&mut app, pos);
+        spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Water, Vec2::new(640.0, 32.0)), pos, 0.0);
+
+        assert_eq!(collisions(&mut app), vec![CollisionEvent::PlayerInWater]);
+    }
+
+    #[test]
+    fn platform_over_water_keeps_player_dry() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        spawn_player(&mut app, pos);
+        spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Water, Vec2::new(640.0, 32.0)), pos, 0.0);
+        let log = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Platform, Vec2::new(96.0, 32.0)), pos, 0.0);
+
+        assert_eq!(collisions(&mut app), vec![CollisionEvent::PlayerOnPlatform(log)]);
+    }
+
+    #[test]
+    fn child_hitbox_is_checked_relative_to_parent() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        spawn_player(&mut app, pos);
+        let log = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Platform, Vec2::new(96.0, 32.0)), pos, -64.0);
+        let snake = app.world_mut().spawn((
+            Hitbox::new(HitboxKind::Enemy, Vec2::new(16.0, 16.0)),
+            Transform::from_xyz(64.0, 0.0, 0.0),
+        )).set_parent(log).id();
+
+        assert_eq!(collisions(&mut app), vec![CollisionEvent::PlayerHitEnemy(snake)]);
+    }
+
+    #[test]
+    fn row_index_insert_moves_and_removes() {
+        let mut world = World::new();
+        let a = world.spawn_empty().id();
+        let b = world.spawn_empty().id();
+        let mut index = RowIndex::default();
+
+        index.insert(a, 1);
+        index.insert(b, 1);
+        index.insert(a, 1);
+        assert_eq!(index.get(1), &[a, b]);
+
+        index.insert(a, 2);
+        assert_eq!(index.get(1), &[b]);
+        assert_eq!(index.get(2), &[a]);
+
+        index.remove(b);
+        index.remove(b);
+        assert!(index.get(1).is_empty());
+        assert_eq!(index.get(2), &[a]);
+        assert!(index.get(3).is_empty());
+    }
+
+    #[test]
+    fn index_follows_grid_pos() {
+        let mut app = app();
+        let log = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Platform, Vec2::splat(32.0)), GridPos::new(5, ROW), 0.0);
+        app.update();
+        assert_eq!(app.world().resource::<RowIndex>().get(ROW), &[log]);
+
+        app.world_mut().get_mut::<GridPos>(log).unwrap().row = ROW + 1;
+        app.update();
+        let index = app.world().resource::<RowIndex>();
+        assert!(index.get(ROW).is_empty());
+        assert_eq!(index.get(ROW + 1), &[log]);
+    }
+
+    #[test]
+    fn player_is_not_indexed() {
+        let mut app = app();
+        spawn_player(&mut app, GridPos::new(5, ROW));
+        app.update();
+
+        assert!(app.world().resource::<RowIndex>().get(ROW).is_empty());
+    }
+
+    #[test]
+    fn removed_hitbox_is_unindexed() {
+        let mut app = app();
+        let car = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, Vec2::splat(32.0)), GridPos::new(5, ROW), 0.0);
+        app.update();
+
+        app.world_mut().entity_mut(car).remove::<Hitbox>();
+        assert!(app.world().resource::<RowIndex>().get(ROW).is_empty());
+    }
+
+    #[test]
+    fn despawned_hierarchy_is_unindexed() {
+        let mut app = app();
+        let pos = GridPos::new(5, ROW);
+        let log = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Platform, Vec2::new(96.0, 32.0)), pos, 0.0);
+        app.world_mut().spawn((
+            Hitbox::new(HitboxKind::Enemy, Vec2::new(16.0, 16.0)),
+            Transform::default(),
+        )).set_parent(log);
+        let car = spawn_hitbox(&mut app, Hitbox::new(HitboxKind::Vehicle, Vec2::splat(32.0)), pos, 0.0);
+        app.update();
+
+        app.world_mut().entity_mut(log).despawn_recursive();
+        assert_eq!(app.world().resource::<RowIndex>().get(ROW), &[car]);
+        assert!(!app.world().resource::<RowIndex>().entries.contains_key(&log));
+
+        // 次の更新で入れ直されることもない
+        app.update();
+        assert_eq!(app.world().resource::<RowIndex>().get(ROW), &[car]);
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::{
+    prelude::*,
+    sprite::Anchor,
+};
+
+use crate::{
+    AppState, GameplaySet, GoalEvent, InGame, MissEvent, ScoreEvent, in_play,
+    difficulty::Difficulty,
+    player::{Dying, Player},
+};
+
+/// 1匹あたりの制限時間（秒）
+const TIME_LIMIT: f32 = 30.0;
+const BAR_SIZE: Vec2 = Vec2::new(256.0, 12.0);
+/// バーの右端の位置
+const BAR_POSITION: Vec3 = Vec3::new(304.0, -224.0, 2.0);
+const COLOR_BAR: Color = Color::srgb(0.2, 0.9, 0.2);
+const COLOR_BAR_LOW: Color = Color::srgb(0.9, 0.2, 0.2);
+/// バーの色が変わる残り時間の割合
+const LOW_FRACTION: f32 = 0.25;
+
+/// プレイヤー1匹ごとの残り時間を管理するリソース
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct Countdown(Timer);
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Self(Timer::from_seconds(TIME_LIMIT, TimerMode::Once))
+    }
+}
+
+impl Countdown {
+    /// 難易度に合わせた制限時間で作る
+    fn new(difficulty: &Difficulty) -> Self {
+        Self(Timer::from_seconds(difficulty.time_limit(TIME_LIMIT), TimerMode::Once))
+    }
+}
+
+/// 残り時間を表示するバーのコンポーネント
+#[derive(Component, Debug)]
+struct CountdownBar;
+
+/// 残り時間のバーを配置する関数
+fn countdown_setup(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("countdown_setup");
+
+    commands.insert_resource(Countdown::new(&difficulty));
+    commands.spawn((
+        Sprite {
+            color: COLOR_BAR,
+            custom_size: Some(BAR_SIZE),
+            anchor: Anchor::CenterRight,
+            ..Default::default()
+        },
+        Transform::from_translation(BAR_POSITION),
+        CountdownBar,
+        StateScoped(InGame),
+    ));
+}
+
+/// 固定タイムステップで残り時間を減らし、時間切れならミスとする関数
+fn countdown_tick(
+    mut events: EventWriter<MissEvent>,
+    mut countdown: ResMut<Countdown>,
+    player_query: Query<(), (With<Player>, Without<Dying>)>,
+    time: Res<Time<Fixed>>,
+) {
+    info_once!("countdown_tick");
+
+    // やられている途中は時間を進めない
+    if player_query.is_empty() {
+        return;
+    }
+
+    if countdown.tick(time.delta()).just_finished() {
+        events.send_default();
+    }
+}
+
+/// ミスまたはゴールしたら残り時間を戻し、ゴールなら残り時間をボーナス点として加算する関数
+fn countdown_reset(
+    mut miss_events: EventReader<MissEvent>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut countdown: ResMut<Countdown>,
+) {
+    info_once!("countdown_reset");
+
+    if !goal_events.is_empty() {
+        goal_events.clear();
+
+        let half_seconds = (countdown.remaining_secs() * 2.0) as u32;
+        score_events.send(ScoreEvent::TimeBonus(half_seconds));
+        countdown.reset();
+    }
+    if !miss_events.is_empty() {
+        miss_events.clear();
+        countdown.reset();
+    }
+}
+
+/// 次のラウンドを始めるときに、新しい難易度の制限時間にする関数
+fn countdown_next_round(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("countdown_next_round");
+
+    commands.insert_resource(Countdown::new(&difficulty));
+}
+
+/// 残り時間に合わせてバーを縮める関数
+fn countdown_bar(
+    mut query: Query<&mut Sprite, With<CountdownBar>>,
+    countdown: Res<Countdown>,
+) {
+    info_once!("countdown_bar");
+
+    let Ok(mut sprite) = query.get_single_mut() else {
+        return;
+    };
+    let fraction = countdown.fraction_remaining();
+
+    sprite.custom_size = Some(Vec2::new(BAR_SIZE.x * fraction, BAR_SIZE.y));
+    sprite.color = if fraction < LOW_FRACTION { COLOR_BAR_LOW } else { COLOR_BAR };
+}
+
+pub struct CountdownPlugin;
+
+impl Plugin for CountdownPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Countdown>()
+            .add_systems(OnEnter(InGame), countdown_setup)
+            .add_systems(OnExit(AppState::RoundClear), countdown_next_round)
+            .add_systems(FixedUpdate, countdown_tick.in_set(GameplaySet::Collision).run_if(in_play))
+            .add_systems(FixedPostUpdate, countdown_reset.run_if(in_state(InGame)))
+            .add_systems(Update, countdown_bar.in_set(GameplaySet::Ui).run_if(in_state(InGame)))
+        ;
+    }
+}
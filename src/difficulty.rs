@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+
+use crate::{
+    InGame,
+    goal::Round,
+    level::Lane,
+    river::{DiveCycle, PlatformKind},
+};
+
+/// ラウンドごとの難易度の表。最後のラウンドより先は最後の難易度のままにする
+const DIFFICULTY_TABLE: [Difficulty; 5] = [
+    Difficulty { speed: 1.0, platform_spacing: 1.0, log_shorten: 0, time: 1.0, turtles_dive: false, enemies: false, bay_crocodiles: false },
+    Difficulty { speed: 1.2, platform_spacing: 1.0, log_shorten: 0, time: 0.9, turtles_dive: false, enemies: true, bay_crocodiles: false },
+    Difficulty { speed: 1.35, platform_spacing: 1.15, log_shorten: 1, time: 0.8, turtles_dive: true, enemies: true, bay_crocodiles: false },
+    Difficulty { speed: 1.5, platform_spacing: 1.3, log_shorten: 1, time: 0.7, turtles_dive: true, enemies: true, bay_crocodiles: true },
+    Difficulty { speed: 1.7, platform_spacing: 1.4, log_shorten: 2, time: 0.6, turtles_dive: true, enemies: true, bay_crocodiles: true },
+];
+/// 短くしても残す丸太の長さ（マス）
+const LOG_MIN_LEN: u8 = 2;
+
+/// 現在のラウンドの難易度を管理するリソース
+/// - speed: 車線と川の流れの速さの倍率
+/// - platform_spacing: 足場同士の間隔の倍率。大きいほど足場が少なくなる
+/// - log_shorten: 丸太を短くする長さ（マス）
+/// - time: 制限時間の倍率
+/// - turtles_dive: 潜る周期を指定していない亀も潜らせるか
+/// - enemies: 敵を出すか。出さないときはワニも丸太にする
+/// - bay_crocodiles: 空いている巣にワニを出すか
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    pub speed: f32,
+    pub platform_spacing: f32,
+    pub log_shorten: u8,
+    pub time: f32,
+    pub turtles_dive: bool,
+    pub enemies: bool,
+    pub bay_crocodiles: bool,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        DIFFICULTY_TABLE[0]
+    }
+}
+
+impl Difficulty {
+    /// 道路の車線の設定に難易度を反映する
+    pub fn road_lane(&self, lane: &Lane) -> Lane {
+        Lane {
+            speed: lane.speed * self.speed,
+            ..lane.clone()
+        }
+    }
+
+    /// 川の流れの設定に難易度を反映する
+    pub fn river_lane(&self, lane: &Lane) -> Lane {
+        Lane {
+            speed: lane.speed * self.speed,
+            spacing: lane.spacing * self.platform_spacing,
+            ..lane.clone()
+        }
+    }
+
+    /// 足場の長さに難易度を反映する
+    pub fn platform(&self, platform: PlatformKind) -> PlatformKind {
+        match platform {
+            PlatformKind::Log(len) => {
+                let min = len.min(LOG_MIN_LEN);
+                PlatformKind::Log(len.saturating_sub(self.log_shorten).max(min))
+            }
+            PlatformKind::Crocodile(len) if !self.enemies => self.platform(PlatformKind::Log(len)),
+            _ => platform,
+        }
+    }
+
+    /// 亀が潜る周期に難易度を反映する
+    pub fn dive(&self, dive: Option<DiveCycle>) -> Option<DiveCycle> {
+        match dive {
+            None if self.turtles_dive => Some(DiveCycle::default()),
+            _ => dive,
+        }
+    }
+
+    /// 制限時間に難易度を反映する
+    pub fn time_limit(&self, secs: f32) -> f32 {
+        secs * self.time
+    }
+}
+
+/// ラウンドごとの難易度の表を管理するリソース
+#[derive(Resource, Debug, Deref)]
+pub struct DifficultyCurve(Vec<Difficulty>);
+
+impl Default for DifficultyCurve {
+    fn default() -> Self {
+        Self(DIFFICULTY_TABLE.to_vec())
+    }
+}
+
+impl DifficultyCurve {
+    /// ラウンドの難易度を返す。表より先のラウンドは最後の難易度を返す
+    pub fn get(&self, round: u32) -> Difficulty {
+        let index = (round.max(1) - 1) as usize;
+
+        self.0.get(index).or(self.0.last()).copied().unwrap_or_default()
+    }
+}
+
+/// ラウンドに合わせて難易度を更新する関数
+fn difficulty_update(
+    mut difficulty: ResMut<Difficulty>,
+    curve: Res<DifficultyCurve>,
+    round: Res<Round>,
+) {
+    info_once!("difficulty_update");
+
+    difficulty.set_if_neq(curve.get(**round));
+}
+
+/// ゲームを終えたら難易度を最初のラウンドに戻す関数
+/// 次のゲームの配置が前のゲームの難易度にならないようにする
+fn difficulty_reset(
+    mut difficulty: ResMut<Difficulty>,
+    curve: Res<DifficultyCurve>,
+) {
+    info_once!("difficulty_reset");
+
+    *difficulty = curve.get(1);
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<DifficultyCurve>()
+            .init_resource::<Difficulty>()
+            .add_systems(OnExit(InGame), difficulty_reset)
+            // ラウンドクリアで増えたラウンドを、ステートが変わる前に反映する
+            .add_systems(FixedPostUpdate, difficulty_update.run_if(in_state(InGame)))
+        ;
+    }
+}
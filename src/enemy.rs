@@ -0,0 +1,335 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    AppState, GameplaySet, InGame, MissEvent, Velocity, WINDOW_SIZE, apply_velocity, in_play,
+    bonus::BonusSystem,
+    collision::{CollisionEvent, Hitbox, HitboxKind, collision_broadphase, collision_index},
+    difficulty::Difficulty,
+    grid::{Grid, GridPos, PlayField},
+    interpolation::Interpolated,
+    level::{Level, LevelReloadEvent},
+    replay::GameRng,
+    river::Log,
+};
+
+const SNAKE_SIZE: Vec2 = Vec2::new(48.0, 12.0);
+const OTTER_SIZE: Vec2 = Vec2::new(28.0, 20.0);
+/// 画面外でカワウソが現れる、または消えるまでの余白
+const OTTER_MARGIN: f32 = 32.0;
+const COLOR_SNAKE: Color = Color::srgb(0.6, 0.2, 0.8);
+const COLOR_OTTER: Color = Color::srgb(0.4, 0.25, 0.1);
+
+/// 敵の種類と配置
+#[derive(Debug, Clone, Deserialize)]
+pub enum EnemyKind {
+    /// 行の端から端までを往復するヘビ
+    Snake { row: i32, speed: f32 },
+    /// 行のどれかの丸太の上を往復するヘビ
+    LogSnake { row: i32, speed: f32 },
+    /// 一定の間隔で川の端から泳いでくるカワウソ
+    /// - interval: 現れる間隔（秒）
+    Otter { row: i32, speed: f32, interval: f32 },
+}
+
+/// 敵の目印のコンポーネント
+#[derive(Component, Debug)]
+struct Enemy;
+
+/// 範囲の端で向きを変えながら往復するコンポーネント
+/// - min: 左端のx座標
+/// - max: 右端のx座標
+#[derive(Component, Debug)]
+struct Patrol {
+    min: f32,
+    max: f32,
+}
+
+/// 丸太の上を往復するコンポーネント
+/// - row: 乗る丸太の行
+/// - log: 乗っている丸太。丸太が消えたら同じ行の別の丸太に乗り直す
+/// - offset: 丸太の中心からのずれ
+/// - speed: 丸太の上を動く速さ
+#[derive(Component, Debug)]
+struct OnLog {
+    row: i32,
+    log: Option<Entity>,
+    offset: f32,
+    speed: f32,
+}
+
+/// カワウソが現れる場所のコンポーネント
+/// - row: 現れる行
+/// - speed: 泳ぐ速さ
+/// - timer: 次に現れるまでのタイマー
+#[derive(Component, Debug)]
+struct OtterDen {
+    row: i32,
+    speed: f32,
+    timer: Timer,
+}
+
+/// 川を泳いで横切るカワウソのコンポーネント
+#[derive(Component, Debug)]
+struct Otter;
+
+/// ステージの敵を配置する
+/// 難易度で敵が出ないときは何もしない
+fn spawn_enemies(commands: &mut Commands, grid: &Grid, play_field: &PlayField, level: &Level, difficulty: &Difficulty) {
+    if !difficulty.enemies {
+        return;
+    }
+
+    for kind in &level.enemies {
+        match *kind {
+            EnemyKind::Snake { row, speed } => {
+                let position = grid.to_world(GridPos::new(play_field.min.col, row));
+                let max = grid.to_world(GridPos::new(play_field.max.col, row)).x;
+                commands.spawn((
+                    Sprite::from_color(COLOR_SNAKE, SNAKE_SIZE),
+                    Transform::from_translation(position.extend(0.5)),
+                    Interpolated::new(position.extend(0.5)),
+                    grid.to_grid(position),
+                    Velocity(Vec2::new(speed * difficulty.speed, 0.0)),
+                    Patrol { min: position.x, max },
+                    Hitbox::new(HitboxKind::Enemy, SNAKE_SIZE),
+                    Enemy,
+                    StateScoped(InGame),
+                ));
+            }
+            EnemyKind::LogSnake { row, speed } => {
+                // 乗る丸太が決まるまでは隠し、当たり判定も無効にしておく
+                let position = grid.to_world(GridPos::new(0, row));
+                commands.spawn((
+                    Sprite::from_color(COLOR_SNAKE, SNAKE_SIZE),
+                    Transform::from_translation(position.extend(0.5)),
+                    Interpolated::new(position.extend(0.5)),
+                    GridPos::new(0, row),
+                    Visibility::Hidden,
+                    OnLog { row, log: None, offset: 0.0, speed: speed * difficulty.speed },
+                    Hitbox {
+                        enabled: false,
+                        ..Hitbox::new(HitboxKind::Enemy, SNAKE_SIZE)
+                    },
+                    Enemy,
+                    StateScoped(InGame),
+                ));
+            }
+            EnemyKind::Otter { row, speed, interval } => {
+                commands.spawn((
+                    OtterDen {
+                        row,
+                        speed: speed * difficulty.speed,
+                        timer: Timer::from_seconds(interval, TimerMode::Repeating),
+                    },
+                    Enemy,
+                    StateScoped(InGame),
+                ));
+            }
+        }
+    }
+}
+
+/// 配置してある敵を消し、今のステージと難易度で配置し直す
+fn respawn_enemies(
+    commands: &mut Commands,
+    query: &Query<Entity, With<Enemy>>,
+    grid: &Grid,
+    play_field: &PlayField,
+    level: &Level,
+    difficulty: &Difficulty,
+) {
+    for entity in query {
+        commands.entity(entity).despawn();
+    }
+    spawn_enemies(commands, grid, play_field, level, difficulty);
+}
+
+/// 敵を配置する関数
+fn enemy_setup(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    play_field: Res<PlayField>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("enemy_setup");
+
+    spawn_enemies(&mut commands, &grid, &play_field, &level, &difficulty);
+}
+
+/// ステージを読み込み直したら敵を配置し直す関数
+fn enemy_reload(
+    mut commands: Commands,
+    mut events: EventReader<LevelReloadEvent>,
+    query: Query<Entity, With<Enemy>>,
+    grid: Res<Grid>,
+    play_field: Res<PlayField>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("enemy_reload");
+
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    respawn_enemies(&mut commands, &query, &grid, &play_field, &level, &difficulty);
+}
+
+/// 次のラウンドを始めるときに、新しい難易度で敵を配置し直す関数
+fn enemy_next_round(
+    mut commands: Commands,
+    query: Query<Entity, With<Enemy>>,
+    grid: Res<Grid>,
+    play_field: Res<PlayField>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("enemy_next_round");
+
+    respawn_enemies(&mut commands, &query, &grid, &play_field, &level, &difficulty);
+}
+
+/// 範囲の端に着いたら向きを変える関数
+fn enemy_patrol(mut query: Query<(&Transform, &mut Velocity, &Patrol)>) {
+    info_once!("enemy_patrol");
+
+    for (transform, mut velocity, patrol) in &mut query {
+        let x = transform.translation.x;
+
+        if (x <= patrol.min && velocity.x < 0.0) || (x >= patrol.max && velocity.x > 0.0) {
+            velocity.x = -velocity.x;
+        }
+    }
+}
+
+/// 丸太の上のヘビを、丸太と一緒に動かしながら丸太の端から端まで往復させる関数
+/// 乗っている丸太がなくなったら、同じ行の一番左の丸太に乗り直す。乗る丸太がなければ当たり判定を無効にする
+fn enemy_log_snake(
+    mut query: Query<(&mut OnLog, &mut Transform, &mut Hitbox)>,
+    log_query: Query<(Entity, &Transform, &Log, &GridPos), Without<OnLog>>,
+    time: Res<Time>,
+) {
+    info_once!("enemy_log_snake");
+
+    for (mut on_log, mut transform, mut hitbox) in &mut query {
+        if on_log.log.is_none_or(|log| !log_query.contains(log)) {
+            let log = log_query
+                .iter()
+                .filter(|(_, _, _, grid_pos)| grid_pos.row == on_log.row)
+                .min_by(|(_, a, _, _), (_, b, _, _)| a.translation.x.total_cmp(&b.translation.x))
+                .map(|(entity, _, _, _)| entity);
+            on_log.log = log;
+            on_log.offset = 0.0;
+        }
+
+        let Some((_, log_transform, log, _)) = on_log.log.and_then(|log| log_query.get(log).ok()) else {
+            hitbox.enabled = false;
+            continue;
+        };
+        hitbox.enabled = true;
+
+        // 丸太からはみ出さない範囲で往復する
+        let limit = ((log.width() - SNAKE_SIZE.x) / 2.0).max(0.0);
+        on_log.offset += on_log.speed * time.delta_secs();
+        if on_log.offset.abs() >= limit {
+            on_log.offset = on_log.offset.clamp(-limit, limit);
+            on_log.speed = -on_log.speed;
+        }
+
+        transform.translation.x = log_transform.translation.x + on_log.offset;
+        transform.translation.y = log_transform.translation.y;
+    }
+}
+
+/// 丸太に乗っていないヘビを隠す関数
+fn enemy_log_snake_sprite(mut query: Query<(&mut Visibility, &Hitbox), With<OnLog>>) {
+    info_once!("enemy_log_snake_sprite");
+
+    for (mut visibility, hitbox) in &mut query {
+        visibility.set_if_neq(if hitbox.enabled { Visibility::Inherited } else { Visibility::Hidden });
+    }
+}
+
+/// 一定の間隔で、川の左右どちらかの端からカワウソを泳がせる関数
+/// 反対側の画面外まで泳いだカワウソは消す
+fn enemy_otter(
+    mut commands: Commands,
+    mut den_query: Query<&mut OtterDen>,
+    otter_query: Query<(Entity, &Transform), With<Otter>>,
+    mut rng: ResMut<GameRng>,
+    grid: Res<Grid>,
+    time: Res<Time>,
+) {
+    info_once!("enemy_otter");
+
+    let edge = WINDOW_SIZE.x / 2.0 + OTTER_MARGIN;
+
+    for (entity, transform) in &otter_query {
+        if transform.translation.x.abs() > edge {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for mut den in &mut den_query {
+        if !den.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // 消える位置のすぐ内側から現れる
+        let sign = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        let position = Vec2::new(-sign * edge * 0.99, grid.to_world(GridPos::new(0, den.row)).y);
+        let velocity = Vec2::new(sign * den.speed, 0.0);
+        commands.spawn((
+            Sprite::from_color(COLOR_OTTER, OTTER_SIZE),
+            Transform::from_translation(position.extend(0.5)),
+            Interpolated::new(position.extend(0.5)),
+            grid.to_grid(position),
+            Velocity(velocity),
+            Otter,
+            Hitbox::new(HitboxKind::Enemy, OTTER_SIZE),
+            Enemy,
+            StateScoped(InGame),
+        ));
+    }
+}
+
+/// プレイヤーが敵に当たったらミスとする関数
+fn enemy_collision(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut events: EventWriter<MissEvent>,
+) {
+    info_once!("enemy_collision");
+
+    let hit = collision_events
+        .read()
+        .any(|event| matches!(event, CollisionEvent::PlayerHitEnemy(_)));
+    if hit {
+        events.send_default();
+    }
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(InGame), enemy_setup)
+            .add_systems(OnExit(AppState::RoundClear), enemy_next_round)
+            .add_systems(Update, enemy_reload.run_if(in_state(InGame)))
+            .add_systems(FixedUpdate, (
+                enemy_patrol.in_set(GameplaySet::Physics).after(apply_velocity),
+                // 丸太が動き終えてから丸太の上の位置を決める
+                (
+                    enemy_otter,
+                    enemy_log_snake,
+                ).chain().in_set(GameplaySet::Collision).after(BonusSystem).before(collision_index),
+                enemy_collision.in_set(GameplaySet::Collision).after(collision_broadphase),
+            ).run_if(in_play))
+            .add_systems(Update, enemy_log_snake_sprite.in_set(GameplaySet::Animation).run_if(in_state(InGame)))
+        ;
+    }
+}
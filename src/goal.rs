@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    AppState, GoalEvent, InGame, MissEvent, RoundClearEvent, ScoreEvent, WINDOW_SIZE, GameplaySet, in_play,
+    bonus::BonusSystem,
+    collision::{CollisionEvent, Hitbox, HitboxKind, collision_broadphase},
+    difficulty::Difficulty,
+    grid::{Grid, GridPos},
+    level::{Level, LevelReloadEvent, RowKind},
+    player::{Dying, Hop, Player, PlayerAtlas},
+    replay::GameRng,
+};
+
+const HOME_SIZE: Vec2 = Vec2::new(40.0, 32.0);
+const COLOR_BANK: Color = Color::srgb(0.1, 0.4, 0.1);
+const COLOR_HOME: Color = Color::srgb(0.0, 0.1, 0.4);
+/// ワニが巣に現れる間隔（秒）
+const BAY_CROCODILE_INTERVAL_SECS: f32 = 9.0;
+/// ワニが巣にいる時間（秒）
+const BAY_CROCODILE_SECS: f32 = 4.0;
+const BAY_CROCODILE_SIZE: Vec2 = Vec2::new(28.0, 20.0);
+const COLOR_BAY_CROCODILE: Color = Color::srgb(0.1, 0.35, 0.1);
+
+/// 現在のラウンド数を管理するリソース
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct Round(pub u32);
+
+/// ゴールとなる巣のコンポーネント
+/// - index: 巣の番号
+/// - filled: プレイヤーがすでにゴールしているか
+/// - crocodile: ワニが頭を出しているか。いる間に入るとミスになる
+#[derive(Component, Debug)]
+pub struct Home {
+    pub index: usize,
+    pub filled: bool,
+    pub crocodile: bool,
+}
+
+/// 巣に置かれたカエルの目印のコンポーネント
+#[derive(Component, Debug)]
+struct HomeMarker;
+
+/// 巣から頭を出しているワニのコンポーネント
+/// 巣の子として配置する
+/// - timer: いなくなるまでのタイマー
+#[derive(Component, Debug)]
+struct BayCrocodile {
+    timer: Timer,
+}
+
+/// 次にワニが巣に現れるまでのタイマーを管理するリソース
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct BayCrocodileTimer(Timer);
+
+impl Default for BayCrocodileTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(BAY_CROCODILE_INTERVAL_SECS, TimerMode::Repeating))
+    }
+}
+
+/// 岸のコンポーネント
+#[derive(Component, Debug)]
+struct Bank;
+
+/// 候補の巣から乱数で1つ選ぶ
+/// 乱数の結果がクエリの順番に左右されないよう、巣の番号順に並べてから選ぶ
+pub fn choose_home<T>(mut homes: Vec<T>, home: impl Fn(&T) -> &Home, rng: &mut GameRng) -> Option<T> {
+    if homes.is_empty() {
+        return None;
+    }
+    homes.sort_by_key(|item| home(item).index);
+    let index = rng.gen_range(0..homes.len());
+    Some(homes.swap_remove(index))
+}
+
+/// ステージの巣の行に岸と巣を配置する
+fn spawn_homes(commands: &mut Commands, grid: &Grid, level: &Level) {
+    let mut index = 0;
+
+    for (row, kind) in level.iter_rows() {
+        let RowKind::Home { cols } = kind else {
+            continue;
+        };
+
+        // 岸
+        let y = grid.to_world(GridPos::new(0, row)).y;
+        commands.spawn((
+            Sprite::from_color(COLOR_BANK, Vec2::new(WINDOW_SIZE.x, grid.cell_size)),
+            Transform::from_xyz(0.0, y, -1.0),
+            Bank,
+            StateScoped(InGame),
+        ));
+
+        // 巣
+        for col in cols {
+            let grid_pos = GridPos::new(*col, row);
+            commands.spawn((
+                Sprite::from_color(COLOR_HOME, HOME_SIZE),
+                Transform::from_translation(grid.to_world(grid_pos).extend(-0.5)),
+                grid_pos,
+                Home { index, filled: false, crocodile: false },
+                Hitbox::new(HitboxKind::Home, HOME_SIZE),
+                StateScoped(InGame),
+            ));
+            index += 1;
+        }
+    }
+}
+
+/// 岸と巣を配置する関数
+fn goal_setup(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    level: Res<Level>,
+) {
+    info_once!("goal_setup");
+
+    commands.insert_resource(Round(1));
+    commands.insert_resource(BayCrocodileTimer::default());
+
+    spawn_homes(&mut commands, &grid, &level);
+}
+
+/// ステージを読み込み直したら岸と巣を配置し直す関数
+/// 埋まっていた巣は空に戻す
+#[allow(clippy::type_complexity)]
+fn goal_reload(
+    mut commands: Commands,
+    mut events: EventReader<LevelReloadEvent>,
+    query: Query<Entity, Or<(With<Home>, With<Bank>)>>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+) {
+    info_once!("goal_reload");
+
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_homes(&mut commands, &grid, &level);
+}
+
+/// 難易度でワニを出すときは、一定の間隔で空いている巣のどれかにワニを出す関数
+/// 出ているワニは時間が経ったらいなくなる
+fn goal_bay_crocodile(
+    mut commands: Commands,
+    mut timer: ResMut<BayCrocodileTimer>,
+    mut rng: ResMut<GameRng>,
+    mut crocodile_query: Query<(Entity, &mut BayCrocodile, &Parent)>,
+    mut home_query: Query<(Entity, &mut Home)>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    info_once!("goal_bay_crocodile");
+
+    let mut active = false;
+    for (entity, mut crocodile, parent) in &mut crocodile_query {
+        if crocodile.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            if let Ok((_, mut home)) = home_query.get_mut(parent.get()) {
+                home.crocodile = false;
+            }
+        } else {
+            active = true;
+        }
+    }
+
+    if !difficulty.bay_crocodiles || !timer.tick(time.delta()).just_finished() || active {
+        return;
+    }
+
+    let homes = home_query.iter_mut().filter(|(_, home)| !home.filled).collect();
+    let Some((entity, mut home)) = choose_home(homes, |(_, home)| home, &mut rng) else {
+        return;
+    };
+
+    home.crocodile = true;
+    commands.entity(entity).with_child((
+        Sprite::from_color(COLOR_BAY_CROCODILE, BAY_CROCODILE_SIZE),
+        Transform::from_xyz(0.0, 0.0, 0.5),
+        BayCrocodile {
+            timer: Timer::from_seconds(BAY_CROCODILE_SECS, TimerMode::Once),
+        },
+    ));
+}
+
+/// プレイヤーが巣の行に着地したときの判定を行う関数
+/// 空いている巣ならゴール、埋まっている巣、ワニのいる巣、巣の間ならミスとする
+/// やられている途中は、ワニが去っても巣に入らないよう判定しない
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn goal_check(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut goal_events: EventWriter<GoalEvent>,
+    mut miss_events: EventWriter<MissEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    player_query: Query<(&GridPos, Has<Hop>), (With<Player>, Without<Dying>)>,
+    mut home_query: Query<&mut Home>,
+    atlas: Res<PlayerAtlas>,
+    level: Res<Level>,
+) {
+    info_once!("goal_check");
+
+    // 巣の行にいないときに届いたイベントも、ここで読み捨てる
+    let at_home = collision_events.read().find_map(|event| match event {
+        CollisionEvent::PlayerAtHome(entity) => Some(*entity),
+        _ => None,
+    });
+
+    let Ok((player_grid_pos, hopping)) = player_query.get_single() else {
+        return;
+    };
+    // 巣に入るのは着地してから
+    if hopping || !level.is_home(player_grid_pos.row) {
+        return;
+    }
+
+    let home = at_home.and_then(|entity| home_query.get_mut(entity).ok().map(|home| (entity, home)));
+
+    let Some((entity, mut home)) = home else {
+        miss_events.send_default();
+        return;
+    };
+    if home.filled || home.crocodile {
+        miss_events.send_default();
+        return;
+    }
+
+    home.filled = true;
+    goal_events.send(GoalEvent(home.index));
+    score_events.send(ScoreEvent::Home);
+    commands.entity(entity).with_child((
+        atlas.sprite(Player::INDICES_TOP.0),
+        Transform::from_xyz(0.0, 0.0, 0.5).with_scale(Vec3::splat(2.0)),
+        HomeMarker,
+    ));
+}
+
+/// ゴールしてすべての巣が埋まったら次のラウンドに進める関数
+fn goal_round_clear(
+    mut goal_events: EventReader<GoalEvent>,
+    mut events: EventWriter<RoundClearEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    home_query: Query<&Home>,
+    mut round: ResMut<Round>,
+) {
+    info_once!("goal_round_clear");
+
+    if goal_events.is_empty() {
+        return;
+    }
+    goal_events.clear();
+
+    if !home_query.iter().all(|home| home.filled) {
+        return;
+    }
+
+    **round += 1;
+    events.send_default();
+    score_events.send(ScoreEvent::RoundClear);
+    info!("round clear: next round {}", **round);
+}
+
+/// ラウンドクリアの表示が終わったら巣を空にする関数
+/// 出ていたワニもいなくなる
+#[allow(clippy::type_complexity)]
+fn goal_next_round(
+    mut commands: Commands,
+    mut home_query: Query<&mut Home>,
+    marker_query: Query<Entity, Or<(With<HomeMarker>, With<BayCrocodile>)>>,
+    mut timer: ResMut<BayCrocodileTimer>,
+) {
+    info_once!("goal_next_round");
+
+    for mut home in &mut home_query {
+        home.filled = false;
+        home.crocodile = false;
+    }
+    for entity in &marker_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    timer.reset();
+}
+
+pub struct GoalPlugin;
+
+impl Plugin for GoalPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Round(1))
+            .init_resource::<BayCrocodileTimer>()
+            .add_systems(OnEnter(InGame), goal_setup)
+            .add_systems(OnExit(AppState::RoundClear), goal_next_round)
+            .add_systems(Update, goal_reload.run_if(in_state(InGame)))
+            .add_systems(FixedUpdate, (
+                // 乱数を使う順番が再生のたびに変わらないよう、ボーナスの前に実行する
+                goal_bay_crocodile.in_set(GameplaySet::Collision).before(BonusSystem),
+                (
+                    goal_check,
+                    goal_round_clear,
+                ).chain().in_set(GameplaySet::Collision).after(collision_broadphase),
+            ).run_if(in_play))
+        ;
+    }
+}
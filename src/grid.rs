@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    WINDOW_SIZE,
+    player::Player,
+};
+
+/// 升目上の位置を表すコンポーネント
+/// - col: 左端を0とする列
+/// - row: 下端を0とする行
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub struct GridPos {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl GridPos {
+    pub const fn new(col: i32, row: i32) -> Self {
+        Self { col, row }
+    }
+
+    /// 升目の移動量だけずらした位置を返す
+    pub fn offset(self, step: IVec2) -> Self {
+        Self::new(self.col + step.x, self.row + step.y)
+    }
+}
+
+/// 升目の大きさと配置を管理するリソース
+/// - cols: 列の数
+/// - rows: 行の数
+/// - cell_size: 1マスの大きさ
+/// - origin: 左下の升目の中心のワールド座標
+#[derive(Resource, Debug)]
+pub struct Grid {
+    pub cols: i32,
+    pub rows: i32,
+    pub cell_size: f32,
+    pub origin: Vec2,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        // 中央の列がx=0になるように列の数を奇数にする
+        let cols = (WINDOW_SIZE.x / Player::DISTANCE) as i32 - 1;
+        let rows = (WINDOW_SIZE.y / Player::DISTANCE) as i32;
+
+        Self::new(cols, rows)
+    }
+}
+
+impl Grid {
+    /// 列と行の数から、画面の中央に並べた升目を作る
+    pub fn new(cols: i32, rows: i32) -> Self {
+        let cell_size = Player::DISTANCE;
+        let origin = -Vec2::new((cols - 1) as f32, (rows - 1) as f32) * cell_size / 2.0;
+
+        Self { cols, rows, cell_size, origin }
+    }
+
+    /// 升目の位置をワールド座標に変換する
+    pub fn to_world(&self, pos: GridPos) -> Vec2 {
+        self.origin + Vec2::new(pos.col as f32, pos.row as f32) * self.cell_size
+    }
+
+    /// ワールド座標を一番近い升目の位置に変換する
+    pub fn to_grid(&self, position: Vec2) -> GridPos {
+        let cell = ((position - self.origin) / self.cell_size).round();
+
+        GridPos::new(cell.x as i32, cell.y as i32)
+    }
+}
+
+/// プレイヤーが動ける範囲を管理するリソース
+/// - min: 左下の升目
+/// - max: 右上の升目
+/// - off_screen_death: 足場に乗ったまま範囲外に運ばれたらミスにするか
+///   falseなら範囲の端で止まり、足場から落ちるまで運ばれない
+#[derive(Resource, Debug)]
+pub struct PlayField {
+    pub min: GridPos,
+    pub max: GridPos,
+    pub off_screen_death: bool,
+}
+
+impl FromWorld for PlayField {
+    fn from_world(world: &mut World) -> Self {
+        Self::new(world.resource::<Grid>())
+    }
+}
+
+impl PlayField {
+    const HUD_ROWS: i32 = 1;
+
+    /// 升目の大きさから範囲を求める
+    /// 上下の1行ずつはHUDに使うため範囲に含めない
+    pub fn new(grid: &Grid) -> Self {
+        let mut play_field = Self {
+            min: GridPos::new(0, 0),
+            max: GridPos::new(0, 0),
+            off_screen_death: true,
+        };
+        play_field.fit(grid);
+
+        play_field
+    }
+
+    /// 升目の大きさが変わったときに範囲を合わせ直す
+    pub fn fit(&mut self, grid: &Grid) {
+        self.min = GridPos::new(0, Self::HUD_ROWS);
+        self.max = GridPos::new(grid.cols - 1, grid.rows - 1 - Self::HUD_ROWS);
+    }
+
+    /// 升目の位置が範囲内にあるか
+    pub fn contains(&self, pos: GridPos) -> bool {
+        (self.min.col..=self.max.col).contains(&pos.col) && (self.min.row..=self.max.row).contains(&pos.row)
+    }
+
+    /// 列を範囲内に収める
+    pub fn clamp_col(&self, col: i32) -> i32 {
+        col.clamp(self.min.col, self.max.col)
+    }
+}
+
+/// 流れているものの升目の位置を、Transformに合わせて更新する関数
+/// プレイヤーの位置は移動の処理で決めるため対象外
+#[allow(clippy::type_complexity)]
+fn grid_sync(
+    mut query: Query<(&Transform, &mut GridPos), (Changed<Transform>, Without<Player>)>,
+    grid: Res<Grid>,
+) {
+    info_once!("grid_sync");
+
+    for (transform, mut grid_pos) in &mut query {
+        grid_pos.set_if_neq(grid.to_grid(transform.translation.truncate()));
+    }
+}
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Grid>()
+            .init_resource::<PlayField>()
+            .add_systems(FixedPostUpdate, grid_sync)
+        ;
+    }
+}
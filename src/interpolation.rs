@@ -0,0 +1,76 @@
+use bevy::{
+    app::RunFixedMainLoopSystem,
+    prelude::*,
+};
+
+/// 1回の固定タイムステップでこれ以上動いたら、折り返しや復活による瞬間移動とみなして補間しない
+const TELEPORT_DISTANCE: f32 = 16.0;
+
+/// 固定タイムステップで動かすエンティティの、描画用の補間に使う位置を管理するコンポーネント
+/// - previous: 1つ前の固定タイムステップの終わりの位置
+/// - current: 最後の固定タイムステップの終わりの位置
+///
+/// 固定タイムステップの間はTransformに`current`を戻してゲームを進め、
+/// それ以外の間はTransformに`previous`と`current`の間の位置を入れて描画する
+#[derive(Component, Debug)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl Interpolated {
+    pub fn new(translation: Vec3) -> Self {
+        Self { previous: translation, current: translation }
+    }
+}
+
+/// 固定タイムステップを始める前に、Transformをゲームの位置に戻す関数
+fn interpolation_restore(mut query: Query<(&mut Transform, &Interpolated)>) {
+    info_once!("interpolation_restore");
+
+    for (mut transform, interpolated) in &mut query {
+        transform.translation = interpolated.current;
+    }
+}
+
+/// 固定タイムステップの終わりに、ゲームの位置を記録する関数
+fn interpolation_store(mut query: Query<(&Transform, &mut Interpolated)>) {
+    info_once!("interpolation_store");
+
+    for (transform, mut interpolated) in &mut query {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+
+        if interpolated.previous.distance(interpolated.current) > TELEPORT_DISTANCE {
+            interpolated.previous = interpolated.current;
+        }
+    }
+}
+
+/// 固定タイムステップの進み具合に合わせて、Transformを描画用の位置にする関数
+fn interpolation_apply(
+    mut query: Query<(&mut Transform, &Interpolated)>,
+    time: Res<Time<Fixed>>,
+) {
+    info_once!("interpolation_apply");
+
+    let t = time.overstep_fraction();
+
+    for (mut transform, interpolated) in &mut query {
+        transform.translation = interpolated.previous.lerp(interpolated.current, t);
+    }
+}
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(RunFixedMainLoop, (
+                interpolation_restore.in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                interpolation_apply.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+            ))
+            .add_systems(FixedLast, interpolation_store)
+        ;
+    }
+}
@@ -1,53 +1,365 @@
-use bevy::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use crate::{Direction, MoveEvent};
+use bevy::{
+    ecs::entity::EntityHashSet,
+    input::InputSystem,
+    prelude::*,
+};
 
-const KEY_PLAYER_LEFT: KeyCode = KeyCode::ArrowLeft;
-const KEY_PLAYER_RIGHT: KeyCode = KeyCode::ArrowRight;
-const KEY_PLAYER_TOP: KeyCode = KeyCode::ArrowUp;
-const KEY_PLAYER_BOTTOM: KeyCode = KeyCode::ArrowDown;
+use serde::{Deserialize, Serialize};
 
-fn key_player_move_left(
-    mut events: EventWriter<MoveEvent>,
+use crate::{
+    Action, ActionEvent, AppState, Direction, GameplaySet, MoveEvent, in_play,
+    player::{Dying, Hop, Player},
+    replay::replaying,
+};
+
+/// 移動以外の操作とキーの対応。1つのキーが複数の操作に対応してもよい
+const KEY_ACTIONS: [(KeyCode, Action); 7] = [
+    (KeyCode::Escape, Action::Pause),
+    (KeyCode::Escape, Action::Back),
+    (KeyCode::Enter, Action::Confirm),
+    (KeyCode::Space, Action::Confirm),
+    (KeyCode::Backspace, Action::Clear),
+    (KeyCode::KeyR, Action::Reset),
+    (KeyCode::KeyO, Action::Options),
+];
+/// ゲームパッドのボタンと操作の対応
+const PAD_ACTIONS: [(GamepadButton, Action); 11] = [
+    (GamepadButton::DPadLeft, Action::MoveLeft),
+    (GamepadButton::DPadRight, Action::MoveRight),
+    (GamepadButton::DPadUp, Action::MoveUp),
+    (GamepadButton::DPadDown, Action::MoveDown),
+    (GamepadButton::Start, Action::Pause),
+    (GamepadButton::Start, Action::Confirm),
+    (GamepadButton::South, Action::Confirm),
+    (GamepadButton::East, Action::Back),
+    (GamepadButton::West, Action::Clear),
+    (GamepadButton::North, Action::Reset),
+    (GamepadButton::Select, Action::Options),
+];
+/// スティックを倒したとみなす傾きの大きさ
+const STICK_DEADZONE: f32 = 0.5;
+/// スティックを戻したとみなす傾きの大きさ。倒したときより小さくして、境目での連続入力を防ぐ
+const STICK_RELEASE: f32 = 0.3;
+/// 設定ファイルを置くディレクトリの名前
+const CONFIG_DIR: &str = "ittoku-frogger";
+/// キー割り当てを保存するファイルの名前
+const CONFIG_FILE_KEY_BINDINGS: &str = "keybindings.ron";
+/// 溜めた入力を捨てるまでの時間（秒）
+const BUFFER_WINDOW_SECS: f32 = 0.25;
+
+/// 入力を操作に変換するシステムのセット
+/// 操作を受け取るシステムはこのセットの後に実行する
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSystem;
+
+/// 跳んでいる途中に押された向きを1つだけ溜めておくリソース
+/// - pending: 溜めている向き。後から押された向きで上書きする
+/// - window: 溜めた入力を捨てるまでの時間
+/// - age: 溜めてからの経過時間
+#[derive(Resource, Debug)]
+pub struct InputBuffer {
+    pub pending: Option<Direction>,
+    pub window: Duration,
+    pub age: Duration,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            window: Duration::from_secs_f32(BUFFER_WINDOW_SECS),
+            age: Duration::ZERO,
+        }
+    }
+}
+
+impl InputBuffer {
+    /// 向きを溜める
+    pub fn push(&mut self, direction: Direction) {
+        self.pending = Some(direction);
+        self.age = Duration::ZERO;
+    }
+
+    /// 溜めている向きを取り出す
+    pub fn take(&mut self) -> Option<Direction> {
+        self.pending.take()
+    }
+
+    /// 溜めている向きを捨てる
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// キー割り当てを保存するファイルの場所を管理するリソース
+/// - 0: ファイルの場所。設定のディレクトリが分からないときはNoneにして、読み書きしない
+#[derive(Resource, Debug, Deref)]
+pub struct KeyBindingsPath(pub Option<PathBuf>);
+
+impl Default for KeyBindingsPath {
+    fn default() -> Self {
+        Self(dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE_KEY_BINDINGS)))
+    }
+}
+
+/// 向きごとに割り当てたキーを管理するリソース
+/// 1つの向きに複数のキーを割り当てられる
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub top: Vec<KeyCode>,
+    pub bottom: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: vec![KeyCode::ArrowLeft, KeyCode::KeyA, KeyCode::Numpad4],
+            right: vec![KeyCode::ArrowRight, KeyCode::KeyD, KeyCode::Numpad6],
+            top: vec![KeyCode::ArrowUp, KeyCode::KeyW, KeyCode::Numpad8],
+            bottom: vec![KeyCode::ArrowDown, KeyCode::KeyS, KeyCode::Numpad2],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// 向きに割り当てたキーを返す
+    pub fn keys(&self, direction: Direction) -> &[KeyCode] {
+        match direction {
+            Direction::Left => &self.left,
+            Direction::Right => &self.right,
+            Direction::Top => &self.top,
+            Direction::Bottom => &self.bottom,
+        }
+    }
+
+    fn keys_mut(&mut self, direction: Direction) -> &mut Vec<KeyCode> {
+        match direction {
+            Direction::Left => &mut self.left,
+            Direction::Right => &mut self.right,
+            Direction::Top => &mut self.top,
+            Direction::Bottom => &mut self.bottom,
+        }
+    }
+
+    /// 移動以外の操作に使うキーかを返す
+    fn is_reserved(key: KeyCode) -> bool {
+        KEY_ACTIONS.iter().any(|(action_key, _)| *action_key == key)
+    }
+
+    /// 向きにキーを追加する。ほかの向きに割り当て済みのキーはそちらから外す
+    /// 移動以外の操作に使うキーは、1回押すと両方の操作になってしまうため割り当てずにfalseを返す
+    pub fn bind(&mut self, direction: Direction, key: KeyCode) -> bool {
+        if Self::is_reserved(key) {
+            return false;
+        }
+        for other in Direction::ALL {
+            self.keys_mut(other).retain(|k| *k != key);
+        }
+        self.keys_mut(direction).push(key);
+        true
+    }
+
+    /// 向きに割り当てたキーをすべて外す
+    pub fn clear(&mut self, direction: Direction) {
+        self.keys_mut(direction).clear();
+    }
+
+    /// 設定ファイルから読み込む。ファイルがない、または読めないときはNoneを返す
+    fn load(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+
+        match ron::from_str(&text) {
+            Ok(key_bindings) => Some(key_bindings),
+            Err(err) => {
+                warn!("failed to parse {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// 設定ファイルに書き込む
+    fn save(&self, path: &Path) {
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("failed to serialize key bindings: {}", err);
+                return;
+            }
+        };
+        let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path, text));
+
+        if let Err(err) = result {
+            warn!("failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// キーボードの入力を操作に変換する関数
+fn key_keyboard_actions(
+    mut events: EventWriter<ActionEvent>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+) {
+    info_once!("key_keyboard_actions");
+
+    for direction in Direction::ALL {
+        if keyboard_input.any_just_pressed(key_bindings.keys(direction).iter().copied()) {
+            events.send(ActionEvent(Action::from_direction(direction)));
+        }
+    }
+    for (key, action) in KEY_ACTIONS {
+        if keyboard_input.just_pressed(key) {
+            events.send(ActionEvent(action));
+        }
+    }
+}
+
+/// ゲームパッドのボタンの入力を操作に変換する関数
+fn key_gamepad_actions(
+    mut events: EventWriter<ActionEvent>,
+    gamepads: Query<&Gamepad>,
 ) {
-    info_once!("key_player_move_left");
+    info_once!("key_gamepad_actions");
 
-    if keyboard_input.just_pressed(KEY_PLAYER_LEFT) {
-        events.send(MoveEvent(Direction::Left));
+    for gamepad in &gamepads {
+        for (button, action) in PAD_ACTIONS {
+            if gamepad.just_pressed(button) {
+                events.send(ActionEvent(action));
+            }
+        }
     }
 }
 
-fn key_player_move_right(
-    mut events: EventWriter<MoveEvent>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+/// ゲームパッドの左スティックを倒したら、その向きの移動の操作に変換する関数
+/// 倒すたびに1回だけ跳ぶよう、一度中央に戻すまでは次の入力を受け付けない
+fn key_gamepad_stick(
+    mut events: EventWriter<ActionEvent>,
+    mut tilted: Local<EntityHashSet>,
+    gamepads: Query<(Entity, &Gamepad)>,
 ) {
-    info_once!("key_player_move_right");
+    info_once!("key_gamepad_stick");
 
-    if keyboard_input.just_pressed(KEY_PLAYER_RIGHT) {
-        events.send(MoveEvent(Direction::Right));
+    for (entity, gamepad) in &gamepads {
+        let stick = gamepad.left_stick();
+        let length = stick.length();
+
+        if length < STICK_RELEASE {
+            tilted.remove(&entity);
+            continue;
+        }
+        if length < STICK_DEADZONE || !tilted.insert(entity) {
+            continue;
+        }
+
+        // 傾きの大きい軸の向きに跳ぶ
+        let direction = if stick.x.abs() > stick.y.abs() {
+            if stick.x < 0.0 { Direction::Left } else { Direction::Right }
+        } else if stick.y < 0.0 {
+            Direction::Bottom
+        } else {
+            Direction::Top
+        };
+        events.send(ActionEvent(Action::from_direction(direction)));
     }
 }
 
-fn key_player_move_top(
-    mut events: EventWriter<MoveEvent>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+/// 移動の操作が入力されたら、その向きを溜める関数
+fn key_player_move(
+    mut events: EventReader<ActionEvent>,
+    mut buffer: ResMut<InputBuffer>,
 ) {
-    info_once!("key_player_move_top");
+    info_once!("key_player_move");
 
-    if keyboard_input.just_pressed(KEY_PLAYER_TOP) {
-        events.send(MoveEvent(Direction::Top));
+    for direction in events.read().filter_map(|event| event.direction()) {
+        buffer.push(direction);
     }
 }
 
-fn key_player_move_bottom(
+/// 溜めた向きを、跳べるようになったら移動イベントとして送る関数
+/// 古くなった入力や、やられている途中の入力は捨てる
+fn key_buffer_flush(
     mut events: EventWriter<MoveEvent>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut buffer: ResMut<InputBuffer>,
+    player_query: Query<(Has<Hop>, Has<Dying>), With<Player>>,
+    time: Res<Time>,
+) {
+    info_once!("key_buffer_flush");
+
+    if buffer.pending.is_none() {
+        return;
+    }
+
+    buffer.age += time.delta();
+    if buffer.age > buffer.window {
+        buffer.clear();
+        return;
+    }
+
+    let Ok((hopping, dying)) = player_query.get_single() else {
+        buffer.clear();
+        return;
+    };
+    if dying {
+        buffer.clear();
+        return;
+    }
+    if hopping {
+        return;
+    }
+
+    if let Some(direction) = buffer.take() {
+        events.send(MoveEvent(direction));
+    }
+}
+
+/// ゲームを止めたら溜めた入力を捨てる関数
+fn key_buffer_clear(mut buffer: ResMut<InputBuffer>) {
+    info_once!("key_buffer_clear");
+
+    buffer.clear();
+}
+
+/// 保存してあるキー割り当てを読み込む関数
+fn key_bindings_load(mut commands: Commands, path: Res<KeyBindingsPath>) {
+    info_once!("key_bindings_load");
+
+    if let Some(key_bindings) = path.as_deref().and_then(KeyBindings::load) {
+        commands.insert_resource(key_bindings);
+    }
+}
+
+/// キー割り当てを保存する関数
+fn key_bindings_save(key_bindings: Res<KeyBindings>, path: Res<KeyBindingsPath>) {
+    info_once!("key_bindings_save");
+
+    if let Some(path) = path.as_deref() {
+        key_bindings.save(path);
+    }
+}
+
+pub fn key_pause(
+    mut events: EventReader<ActionEvent>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
 ) {
-    info_once!("key_player_move_bottom");
+    info_once!("key_pause");
+
+    if !events.read().any(|event| **event == Action::Pause) {
+        return;
+    }
 
-    if keyboard_input.just_pressed(KEY_PLAYER_BOTTOM) {
-        events.send(MoveEvent(Direction::Bottom));
+    match state.get() {
+        AppState::Playing => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::Playing),
+        _ => {}
     }
 }
 
@@ -56,12 +368,140 @@ pub struct KeyPlugin;
 impl Plugin for KeyPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(Update, (
-                key_player_move_left,
-                key_player_move_right,
-                key_player_move_top,
-                key_player_move_bottom,
-            ))
+        .init_resource::<InputBuffer>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<KeyBindingsPath>()
+        .add_systems(Startup, key_bindings_load)
+        .add_systems(OnExit(AppState::Options), key_bindings_save)
+        // 入力の更新の直後に操作と移動イベントを送り、同じフレームのUpdateで処理させる
+        .configure_sets(PreUpdate, ActionSystem.after(InputSystem))
+        .add_systems(PreUpdate, (
+                key_keyboard_actions,
+                key_gamepad_actions,
+                key_gamepad_stick,
+            ).in_set(ActionSystem))
+        .add_systems(PreUpdate, key_player_move.after(ActionSystem).run_if(in_state(AppState::Playing)))
+        // 移動イベントは固定タイムステップで送り、記録と再生の単位をそろえる
+        .add_systems(FixedUpdate, key_buffer_flush.in_set(GameplaySet::Input).run_if(in_play.and(not(replaying))))
+        .add_systems(OnExit(AppState::Playing), key_buffer_clear)
+        // 一時停止できない画面で押されたEscキーも、ここで読み捨てる
+        .add_systems(Update, key_pause)
         ;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// 溜めた入力を送り出すシステムを1回だけ実行し、送られた向きを返す
+    fn flush(world: &mut World, delta: Duration) -> Vec<Direction> {
+        world.resource_mut::<Time>().advance_by(delta);
+        world.run_system_once(key_buffer_flush).unwrap();
+
+        let events = world.resource::<Events<MoveEvent>>();
+        events.get_cursor().read(events).map(|event| **event).collect()
+    }
+
+    fn world_with_player() -> (World, Entity) {
+        let mut world = World::new();
+        world.init_resource::<InputBuffer>();
+        world.init_resource::<Events<MoveEvent>>();
+        world.insert_resource(Time::<()>::default());
+        let player = world.spawn(Player::new()).id();
+        (world, player)
+    }
+
+    #[test]
+    fn push_keeps_latest_direction() {
+        let mut buffer = InputBuffer::default();
+        buffer.push(Direction::Left);
+        buffer.age = Duration::from_millis(100);
+        buffer.push(Direction::Top);
+
+        assert_eq!(buffer.age, Duration::ZERO);
+        assert_eq!(buffer.take(), Some(Direction::Top));
+        assert_eq!(buffer.take(), None);
+    }
+
+    #[test]
+    fn bind_moves_key_between_directions() {
+        let mut key_bindings = KeyBindings::default();
+
+        assert!(key_bindings.bind(Direction::Top, KeyCode::KeyW));
+        assert!(key_bindings.bind(Direction::Left, KeyCode::KeyW));
+        assert!(!key_bindings.keys(Direction::Top).contains(&KeyCode::KeyW));
+        assert!(key_bindings.keys(Direction::Left).contains(&KeyCode::KeyW));
+    }
+
+    #[test]
+    fn bind_rejects_action_keys() {
+        let mut key_bindings = KeyBindings::default();
+
+        for (key, _) in KEY_ACTIONS {
+            assert!(!key_bindings.bind(Direction::Top, key));
+        }
+        assert_eq!(key_bindings, KeyBindings::default());
+    }
+
+    #[test]
+    fn flush_sends_pending_direction() {
+        let (mut world, _) = world_with_player();
+        world.resource_mut::<InputBuffer>().push(Direction::Right);
+
+        assert_eq!(flush(&mut world, Duration::from_millis(16)), vec![Direction::Right]);
+        assert_eq!(world.resource::<InputBuffer>().pending, None);
+    }
+
+    #[test]
+    fn flush_waits_while_hopping() {
+        let (mut world, player) = world_with_player();
+        world.entity_mut(player).insert(Hop::new(Vec2::ZERO, Vec2::Y));
+        world.resource_mut::<InputBuffer>().push(Direction::Top);
+
+        assert!(flush(&mut world, Duration::from_millis(100)).is_empty());
+        assert_eq!(world.resource::<InputBuffer>().pending, Some(Direction::Top));
+
+        world.entity_mut(player).remove::<Hop>();
+        assert_eq!(flush(&mut world, Duration::from_millis(100)), vec![Direction::Top]);
+    }
+
+    #[test]
+    fn flush_drops_stale_input() {
+        let (mut world, player) = world_with_player();
+        world.entity_mut(player).insert(Hop::new(Vec2::ZERO, Vec2::Y));
+        world.resource_mut::<InputBuffer>().push(Direction::Top);
+
+        let window = world.resource::<InputBuffer>().window;
+        assert!(flush(&mut world, window).is_empty());
+        assert_eq!(world.resource::<InputBuffer>().pending, Some(Direction::Top));
+
+        // 窓を過ぎたら、跳び終わっても送らない
+        assert!(flush(&mut world, Duration::from_millis(1)).is_empty());
+        assert_eq!(world.resource::<InputBuffer>().pending, None);
+        world.entity_mut(player).remove::<Hop>();
+        assert!(flush(&mut world, Duration::from_millis(1)).is_empty());
+    }
+
+    #[test]
+    fn flush_drops_input_while_dying() {
+        let (mut world, player) = world_with_player();
+        world.entity_mut(player).insert(Dying(Timer::from_seconds(1.0, TimerMode::Once)));
+        world.resource_mut::<InputBuffer>().push(Direction::Left);
+
+        assert!(flush(&mut world, Duration::from_millis(16)).is_empty());
+        assert_eq!(world.resource::<InputBuffer>().pending, None);
+    }
+
+    #[test]
+    fn flush_drops_input_without_player() {
+        let (mut world, player) = world_with_player();
+        world.despawn(player);
+        world.resource_mut::<InputBuffer>().push(Direction::Bottom);
+
+        assert!(flush(&mut world, Duration::from_millis(16)).is_empty());
+        assert_eq!(world.resource::<InputBuffer>().pending, None);
+    }
+}
@@ -0,0 +1,323 @@
+use std::error::Error;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext, LoadState},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState, Direction, PATH_LEVEL,
+    enemy::EnemyKind,
+    grid::{Grid, GridPos, PlayField},
+    river::{DiveCycle, PlatformKind},
+    traffic::Vehicle,
+};
+
+/// 車線や川の流れの設定
+/// - direction: 流れる向き。LeftかRightのみ
+/// - speed: 流れる速さ
+/// - spacing: 車両や足場同士の間隔
+#[derive(Debug, Clone, Deserialize)]
+pub struct Lane {
+    pub direction: Direction,
+    pub speed: f32,
+    pub spacing: f32,
+}
+
+impl Lane {
+    /// 流れる向きと速さから速度を求める
+    pub fn velocity(&self) -> Vec2 {
+        match self.direction {
+            Direction::Left => Vec2::new(-self.speed, 0.0),
+            Direction::Right => Vec2::new(self.speed, 0.0),
+            // 読み込むときに弾いているので、ここには来ない
+            Direction::Top | Direction::Bottom => Vec2::ZERO,
+        }
+    }
+
+    /// 折り返し後も間隔が揃うように、折り返す幅に並べる個数と間隔を求める
+    pub fn layout(&self, span: f32) -> (usize, f32) {
+        let count = (span / self.spacing).round().max(1.0) as usize;
+
+        (count, span / count as f32)
+    }
+
+    /// 速さと間隔が使える値か調べる。間隔が0以下だと並べる個数が求まらない
+    fn validate(&self) -> Result<(), String> {
+        if matches!(self.direction, Direction::Top | Direction::Bottom) {
+            return Err(format!("invalid lane direction: {:?} (must be Left or Right)", self.direction));
+        }
+        if !self.speed.is_finite() || self.speed < 0.0 {
+            return Err(format!("invalid lane speed: {}", self.speed));
+        }
+        if !self.spacing.is_finite() || self.spacing <= 0.0 {
+            return Err(format!("invalid lane spacing: {}", self.spacing));
+        }
+
+        Ok(())
+    }
+}
+
+/// 秒数がタイマーに使える値か調べる
+fn validate_secs(name: &str, secs: f32) -> Result<(), String> {
+    if !secs.is_finite() || secs < 0.0 {
+        return Err(format!("invalid {}: {}", name, secs));
+    }
+
+    Ok(())
+}
+
+/// 行の種類
+#[derive(Debug, Clone, Deserialize)]
+pub enum RowKind {
+    /// 何もない安全な行
+    Safe,
+    /// 車両が走る道路
+    Road { lane: Lane, vehicle: Vehicle },
+    /// 足場が流れる川
+    /// - dive: 亀が潜る周期。省略すると潜らない
+    River {
+        lane: Lane,
+        platform: PlatformKind,
+        #[serde(default)]
+        dive: Option<DiveCycle>,
+    },
+    /// 巣が並ぶ岸
+    /// - cols: 巣のある列
+    Home { cols: Vec<i32> },
+}
+
+/// ステージの構成を表すアセット
+/// 読み込んだ後はリソースとしても使う
+/// - cols: 列の数
+/// - start: プレイヤーのスタート地点
+/// - rows: 下の行から順に並べた行の種類。行の数は要素の数になる
+/// - enemies: 敵の種類と配置
+#[derive(Asset, Resource, TypePath, Debug, Clone, Deserialize)]
+pub struct Level {
+    pub cols: i32,
+    pub start: GridPos,
+    pub rows: Vec<RowKind>,
+    #[serde(default)]
+    pub enemies: Vec<EnemyKind>,
+}
+
+impl Level {
+    /// 行の種類を返す
+    pub fn row(&self, row: i32) -> Option<&RowKind> {
+        usize::try_from(row).ok().and_then(|row| self.rows.get(row))
+    }
+
+    /// 行の番号と種類を下の行から順に返す
+    pub fn iter_rows(&self) -> impl Iterator<Item = (i32, &RowKind)> {
+        self.rows.iter().enumerate().map(|(row, kind)| (row as i32, kind))
+    }
+
+    /// 巣が並ぶ行か
+    pub fn is_home(&self, row: i32) -> bool {
+        matches!(self.row(row), Some(RowKind::Home { .. }))
+    }
+
+    /// 升目の中にあるか
+    fn contains(&self, pos: GridPos) -> bool {
+        (0..self.cols).contains(&pos.col) && (0..self.rows.len() as i32).contains(&pos.row)
+    }
+
+    /// 配置できない値がないか調べる
+    /// 足場の長さが0だと当たり判定の大きさが負になり、負の秒数はタイマーを作れない
+    fn validate(&self) -> Result<(), String> {
+        if self.cols <= 0 || self.rows.is_empty() {
+            return Err(format!("invalid level size: {} cols, {} rows", self.cols, self.rows.len()));
+        }
+        if !self.contains(self.start) {
+            return Err(format!("start is outside the level: {:?}", self.start));
+        }
+
+        for (row, kind) in self.iter_rows() {
+            self.validate_row(kind).map_err(|err| format!("row {}: {}", row, err))?;
+        }
+
+        for enemy in &self.enemies {
+            let (row, speed) = match *enemy {
+                EnemyKind::Snake { row, speed } | EnemyKind::LogSnake { row, speed } => (row, speed),
+                EnemyKind::Otter { row, speed, interval } => {
+                    if !interval.is_finite() || interval <= 0.0 {
+                        return Err(format!("invalid otter interval: {}", interval));
+                    }
+                    (row, speed)
+                }
+            };
+            if self.row(row).is_none() {
+                return Err(format!("enemy row is outside the level: {}", row));
+            }
+            if !speed.is_finite() || speed < 0.0 {
+                return Err(format!("invalid enemy speed: {}", speed));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 行の設定に配置できない値がないか調べる
+    fn validate_row(&self, kind: &RowKind) -> Result<(), String> {
+        match kind {
+            RowKind::Safe => {}
+            RowKind::Road { lane, .. } => lane.validate()?,
+            RowKind::River { lane, platform, dive } => {
+                lane.validate()?;
+
+                let (PlatformKind::Log(len) | PlatformKind::Turtles(len) | PlatformKind::Crocodile(len)) = platform;
+                if *len == 0 {
+                    return Err(format!("invalid platform length: {:?}", platform));
+                }
+                if let Some(dive) = dive {
+                    validate_secs("dive surface", dive.surface)?;
+                    validate_secs("dive warning", dive.warning)?;
+                    validate_secs("dive submerged", dive.submerged)?;
+                    validate_secs("dive resurface", dive.resurface)?;
+                }
+            }
+            RowKind::Home { cols } => {
+                if let Some(col) = cols.iter().find(|col| !(0..self.cols).contains(*col)) {
+                    return Err(format!("home col is outside the level: {}", col));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 升目の大きさと配置を求める
+    fn grid(&self) -> Grid {
+        Grid::new(self.cols, self.rows.len() as i32)
+    }
+}
+
+/// ステージのファイルを読み込むローダー
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        // 読めない値のまま配置すると止まったりパニックしたりするため、読み込みの時点で弾く
+        let level: Level = ron::de::from_bytes(&bytes)?;
+        level.validate()?;
+
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// ステージのファイルが書き換えられ、読み込み直したことを知らせるイベント
+#[derive(Event, Default)]
+pub struct LevelReloadEvent;
+
+/// 読み込んでいるステージのハンドルを保持するリソース
+#[derive(Resource, Debug, Deref)]
+pub struct LevelHandle(pub Handle<Level>);
+
+/// ステージに合わせて升目と動ける範囲を更新し、ステージをリソースにする
+fn apply_level(commands: &mut Commands, grid: &mut Grid, play_field: &mut PlayField, level: &Level) {
+    *grid = level.grid();
+    play_field.fit(grid);
+    commands.insert_resource(level.clone());
+}
+
+/// ステージのファイルの読み込みを始める関数
+fn level_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    info_once!("level_setup");
+
+    commands.insert_resource(LevelHandle(asset_server.load(PATH_LEVEL)));
+}
+
+/// ステージを読み込み終えたらタイトル画面に進める関数
+/// 読み込めなかったときは、ほかにステージがないためゲームを終える
+#[allow(clippy::too_many_arguments)]
+fn level_loading(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit_events: EventWriter<AppExit>,
+    mut grid: ResMut<Grid>,
+    mut play_field: ResMut<PlayField>,
+    levels: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    handle: Res<LevelHandle>,
+) {
+    info_once!("level_loading");
+
+    if let Some(LoadState::Failed(err)) = asset_server.get_load_state(&**handle) {
+        error!("failed to load {}: {}", PATH_LEVEL, err);
+        exit_events.send(AppExit::error());
+        return;
+    }
+    let Some(level) = levels.get(&**handle) else {
+        return;
+    };
+
+    apply_level(&mut commands, &mut grid, &mut play_field, level);
+    next_state.set(AppState::Title);
+}
+
+/// ステージのファイルが書き換えられたら読み込み直す関数
+fn level_reload(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<Level>>,
+    mut events: EventWriter<LevelReloadEvent>,
+    mut grid: ResMut<Grid>,
+    mut play_field: ResMut<PlayField>,
+    levels: Res<Assets<Level>>,
+    handle: Res<LevelHandle>,
+) {
+    info_once!("level_reload");
+
+    let modified = asset_events
+        .read()
+        .any(|event| event.is_modified(handle.id()));
+    if !modified {
+        return;
+    }
+    let Some(level) = levels.get(&**handle) else {
+        return;
+    };
+
+    info!("level reloaded: {}", PATH_LEVEL);
+    apply_level(&mut commands, &mut grid, &mut play_field, level);
+    events.send_default();
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .add_event::<LevelReloadEvent>()
+            .add_systems(Startup, level_setup)
+            // Updateのシステムが同じフレームで新しいステージを使えるよう、PreUpdateで更新する
+            .add_systems(PreUpdate, (
+                level_loading.run_if(in_state(AppState::Loading)),
+                level_reload.run_if(not(in_state(AppState::Loading))),
+            ))
+        ;
+    }
+}
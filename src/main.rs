@@ -3,21 +3,157 @@ use bevy::{
     log::LogPlugin,
     asset::AssetMetaCheck,
 };
+use serde::{Deserialize, Serialize};
 
+mod bonus;
+mod collision;
+mod countdown;
+mod difficulty;
+mod enemy;
+mod goal;
+mod grid;
+mod interpolation;
 mod key;
+mod level;
+mod options;
 mod player;
+mod replay;
+mod river;
+mod score;
+mod screen;
+#[cfg(test)]
+mod testing;
+mod touch;
+mod traffic;
 
 const GAMETITLE: &str = "いっとくフロッガー";
 const WINDOW_SIZE: Vec2 = Vec2::new(640.0, 480.0);
 const BACKGROUND_COLOR: Color = Color::srgb(0.0, 0.0, 0.0);
 const LOG_FILTER: &str = "info,wgpu_core=warn,wgpu_hal=warn,ittoku_frogger=debug";
 const PATH_IMAGE_PLAYER: &str = "ittoku-frogger/player.png";
+const PATH_LEVEL: &str = "levels/level1.ron";
 
 
 #[derive(Event, Deref, DerefMut)]
 struct MoveEvent(Direction);
 
-#[derive(Debug)]
+/// 入力の種類をまとめた操作
+/// キーボード、ゲームパッド、タッチのどれから入力しても同じ操作として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Pause,
+    Confirm,
+    Back,
+    /// キー割り当て画面で選んでいる向きのキーをすべて外す
+    Clear,
+    /// キー割り当てを最初の状態に戻す
+    Reset,
+    /// タイトル画面からキー割り当て画面を開く
+    Options,
+}
+
+impl Action {
+    /// 向きを移動の操作に変換する
+    fn from_direction(direction: Direction) -> Self {
+        match direction {
+            Direction::Left => Action::MoveLeft,
+            Direction::Right => Action::MoveRight,
+            Direction::Top => Action::MoveUp,
+            Direction::Bottom => Action::MoveDown,
+        }
+    }
+
+    /// 移動の操作を向きに変換する。移動以外の操作ならNoneを返す
+    fn direction(&self) -> Option<Direction> {
+        match self {
+            Action::MoveLeft => Some(Direction::Left),
+            Action::MoveRight => Some(Direction::Right),
+            Action::MoveUp => Some(Direction::Top),
+            Action::MoveDown => Some(Direction::Bottom),
+            Action::Pause | Action::Confirm | Action::Back | Action::Clear | Action::Reset | Action::Options => None,
+        }
+    }
+}
+
+/// 操作が入力されたことを知らせるイベント
+/// - 0: 入力された操作
+#[derive(Event, Debug, Deref)]
+struct ActionEvent(Action);
+
+/// プレイヤーが跳び終わって着地したことを知らせるイベント
+/// - 0: 着地した升目の行
+#[derive(Event, Deref)]
+struct LandEvent(i32);
+
+/// プレイヤーがミスしたことを知らせるイベント
+#[derive(Event, Default)]
+struct MissEvent;
+
+/// プレイヤーがゴールしたことを知らせるイベント
+/// - 0: ゴールした巣の番号
+#[derive(Event, Deref)]
+struct GoalEvent(usize);
+
+/// すべての巣が埋まり、ラウンドをクリアしたことを知らせるイベント
+#[derive(Event, Default)]
+struct RoundClearEvent;
+
+/// 得点が入ったことを知らせるイベント
+#[derive(Event, Debug, Clone, Copy)]
+enum ScoreEvent {
+    /// これまでより先の行に進んだ
+    Step,
+    /// 巣に入った
+    Home,
+    /// 巣に入ったときの残り時間（0.5秒単位）
+    TimeBonus(u32),
+    /// すべての巣を埋めた
+    RoundClear,
+    /// ハエのいる巣に入った
+    Fly,
+    /// メスのカエルを連れて巣に入った
+    LadyFrog,
+}
+
+/// プレイヤーが残っていない状態でミスし、ゲームオーバーになったことを知らせるイベント
+#[derive(Event, Default)]
+struct GameOverEvent;
+
+/// ゲーム全体の状態
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+enum AppState {
+    /// ステージを読み込んでいる間
+    #[default]
+    Loading,
+    Title,
+    Options,
+    Playing,
+    Paused,
+    RoundClear,
+    GameOver,
+}
+
+/// ゲームが始まってから終わるまでの間を表すステート
+/// プレイ中、一時停止中、ラウンドクリア中のときに存在する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = AppState;
+
+    fn compute(sources: AppState) -> Option<Self> {
+        match sources {
+            AppState::Playing | AppState::Paused | AppState::RoundClear => Some(InGame),
+            AppState::Loading | AppState::Title | AppState::Options | AppState::GameOver => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum Direction {
     Left,
     Right,
@@ -25,6 +161,44 @@ enum Direction {
     Bottom
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Top, Direction::Bottom];
+
+    /// 向きを升目の移動量に変換する
+    fn step(&self) -> IVec2 {
+        match self {
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+            Direction::Top => IVec2::Y,
+            Direction::Bottom => IVec2::NEG_Y,
+        }
+    }
+}
+
+#[derive(Component, Debug, Deref, DerefMut)]
+struct Velocity(Vec2);
+
+/// ゲームのシステムを実行する順番
+/// 毎回同じ順番で実行し、同じ入力から同じ結果になるようにする
+/// Input、Movement、Physics、CollisionはFixedUpdate、AnimationとUiはUpdateで使う
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameplaySet {
+    /// 移動イベントを送る
+    Input,
+    /// 移動イベントに合わせてプレイヤーを動かす
+    Movement,
+    /// 速度に合わせて車両や足場を動かす
+    Physics,
+    /// 衝突やゴールを判定する
+    /// 判定しないタイムステップでも衝突のイベントは読み切り、次のタイムステップに持ち越さない
+    Collision,
+    /// スプライトのアニメーションを更新する
+    Animation,
+    /// HUDなどの表示を更新する
+    Ui,
+}
+
+
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins
@@ -46,17 +220,89 @@ fn main() {
                 ..Default::default()
             })
         )
-        .add_event::<MoveEvent>()
-        .insert_resource(ClearColor(BACKGROUND_COLOR))
-        .insert_resource(Time::<Fixed>::from_seconds(1.0 / 60.0))
-        .add_systems(Startup, setup)
-        .add_plugins(key::KeyPlugin)
-        .add_plugins(player::PlayerPlugin)
+        .add_plugins(GamePlugin)
         .run();
 }
 
+/// ゲームのステート、イベント、各モジュールのプラグインをまとめたプラグイン
+/// ウィンドウや描画のプラグインは含まないので、テストでは画面なしで動かせる
+struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<AppState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<AppState>()
+            .enable_state_scoped_entities::<InGame>()
+            .add_event::<MoveEvent>()
+            .add_event::<ActionEvent>()
+            .add_event::<LandEvent>()
+            .add_event::<MissEvent>()
+            .add_event::<GoalEvent>()
+            .add_event::<RoundClearEvent>()
+            .add_event::<ScoreEvent>()
+            .add_event::<GameOverEvent>()
+            .insert_resource(ClearColor(BACKGROUND_COLOR))
+            .insert_resource(Time::<Fixed>::from_seconds(1.0 / 60.0))
+            .add_systems(Startup, setup)
+            .configure_sets(FixedUpdate, (
+                GameplaySet::Input,
+                GameplaySet::Movement,
+                GameplaySet::Physics,
+                GameplaySet::Collision,
+            ).chain())
+            .configure_sets(Update, (
+                GameplaySet::Animation,
+                GameplaySet::Ui,
+            ).chain())
+            .add_systems(FixedUpdate, apply_velocity.in_set(GameplaySet::Physics).run_if(in_play))
+            .add_plugins(grid::GridPlugin)
+            .add_plugins(interpolation::InterpolationPlugin)
+            .add_plugins(level::LevelPlugin)
+            .add_plugins(key::KeyPlugin)
+            .add_plugins(touch::TouchPlugin)
+            .add_plugins(player::PlayerPlugin)
+            .add_plugins(traffic::TrafficPlugin)
+            .add_plugins(river::RiverPlugin)
+            .add_plugins(goal::GoalPlugin)
+            .add_plugins(bonus::BonusPlugin)
+            .add_plugins(enemy::EnemyPlugin)
+            .add_plugins(collision::CollisionPlugin)
+            .add_plugins(countdown::CountdownPlugin)
+            .add_plugins(difficulty::DifficultyPlugin)
+            .add_plugins(score::ScorePlugin)
+            .add_plugins(screen::ScreenPlugin)
+            .add_plugins(options::OptionsPlugin)
+            .add_plugins(replay::ReplayPlugin)
+        ;
+    }
+}
+
 fn setup(mut commands: Commands) {
     info_once!("setup");
 
-    commands.spawn(Camera2d::default());
+    commands.spawn(Camera2d);
+}
+
+/// プレイ中で、ほかのステートへの切り替えを待っていないかを返す関数
+/// ステートは次のフレームで切り替わるため、同じフレームの残りの固定タイムステップでゲームを進めないようにする
+fn in_play(
+    state: Res<State<AppState>>,
+    next_state: Res<NextState<AppState>>,
+) -> bool {
+    *state.get() == AppState::Playing && matches!(*next_state, NextState::Unchanged)
+}
+
+/// 固定タイムステップで、速度に応じてコンポーネントを移動する関数
+fn apply_velocity(
+    mut query: Query<(&mut Transform, &Velocity), With<Velocity>>,
+    time_step: Res<Time<Fixed>>,
+) {
+    info_once!("apply_velocity");
+
+    for (mut transform, velocity) in &mut query {
+        transform.translation.x += velocity.x * time_step.delta().as_secs_f32();
+        transform.translation.y += velocity.y * time_step.delta().as_secs_f32();
+    }
 }
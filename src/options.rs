@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+use crate::{
+    Action, ActionEvent, AppState, Direction,
+    key::KeyBindings,
+};
+
+const TITLE_FONT_SIZE: f32 = 32.0;
+const ROW_FONT_SIZE: f32 = 20.0;
+const HINT_FONT_SIZE: f32 = 16.0;
+const COLOR_ROW: Color = Color::srgb(0.9, 0.9, 0.9);
+const COLOR_SELECTED: Color = Color::srgb(1.0, 0.9, 0.2);
+const COLOR_WAITING: Color = Color::srgb(0.2, 0.9, 0.2);
+const COLOR_HINT: Color = Color::srgb(0.5, 0.5, 0.5);
+
+/// キー割り当て画面のカーソルを管理するリソース
+/// - selected: 選んでいる向きの番号
+/// - waiting: 割り当てるキーが押されるのを待っているか
+#[derive(Resource, Debug, Default)]
+pub struct OptionsCursor {
+    selected: usize,
+    waiting: bool,
+}
+
+impl OptionsCursor {
+    fn direction(&self) -> Direction {
+        Direction::ALL[self.selected]
+    }
+}
+
+/// 向きごとのキー割り当てを表示する行のコンポーネント
+#[derive(Component, Debug)]
+struct OptionsRow(usize);
+
+/// 向きの表示名を返す
+fn label(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "LEFT",
+        Direction::Right => "RIGHT",
+        Direction::Top => "UP",
+        Direction::Bottom => "DOWN",
+    }
+}
+
+/// キー割り当て画面を表示する関数
+fn options_setup(mut commands: Commands) {
+    info_once!("options_setup");
+
+    commands.insert_resource(OptionsCursor::default());
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..Default::default()
+            },
+            StateScoped(AppState::Options),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("KEY BINDINGS"),
+                TextFont::from_font_size(TITLE_FONT_SIZE),
+            ));
+            for i in 0..Direction::ALL.len() {
+                parent.spawn((
+                    Text::default(),
+                    TextFont::from_font_size(ROW_FONT_SIZE),
+                    TextColor(COLOR_ROW),
+                    OptionsRow(i),
+                ));
+            }
+            parent.spawn((
+                Text::new("Up/Down: Select  Enter: Add Key  Backspace: Clear  R: Reset  Esc: Back"),
+                TextFont::from_font_size(HINT_FONT_SIZE),
+                TextColor(COLOR_HINT),
+            ));
+        });
+}
+
+/// キー割り当て画面の入力を処理する関数
+pub fn options_input(
+    mut events: EventReader<ActionEvent>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<OptionsCursor>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    info_once!("options_input");
+
+    let actions: Vec<Action> = events.read().map(|event| **event).collect();
+
+    // 割り当てるキーを待っている間は、戻る操作なら取り消し、それ以外は押されたキーを割り当てる
+    // 決定などに使うキーは割り当てられないので、そのまま次のキーを待つ
+    if cursor.waiting {
+        if actions.contains(&Action::Back) {
+            cursor.waiting = false;
+        } else if let Some(key) = keyboard_input.get_just_pressed().next().copied() {
+            cursor.waiting = !key_bindings.bind(cursor.direction(), key);
+        }
+        return;
+    }
+
+    let count = Direction::ALL.len();
+
+    for action in actions {
+        match action {
+            Action::MoveUp => cursor.selected = (cursor.selected + count - 1) % count,
+            Action::MoveDown => cursor.selected = (cursor.selected + 1) % count,
+            Action::Confirm => cursor.waiting = true,
+            Action::Back => next_state.set(AppState::Title),
+            Action::Clear => key_bindings.clear(cursor.direction()),
+            Action::Reset => *key_bindings = KeyBindings::default(),
+            _ => {}
+        }
+    }
+}
+
+/// キー割り当ての表示を更新する関数
+fn options_text(
+    mut query: Query<(&mut Text, &mut TextColor, &OptionsRow)>,
+    cursor: Res<OptionsCursor>,
+    key_bindings: Res<KeyBindings>,
+) {
+    info_once!("options_text");
+
+    for (mut text, mut color, row) in &mut query {
+        let direction = Direction::ALL[row.0];
+        let keys: Vec<String> = key_bindings.keys(direction).iter().map(|key| format!("{:?}", key)).collect();
+        let selected = row.0 == cursor.selected;
+
+        **text = if selected && cursor.waiting {
+            format!("{:<6} Press a key ...", label(direction))
+        } else {
+            format!("{:<6} {}", label(direction), keys.join(", "))
+        };
+        color.0 = match (selected, cursor.waiting) {
+            (true, true) => COLOR_WAITING,
+            (true, false) => COLOR_SELECTED,
+            _ => COLOR_ROW,
+        };
+    }
+}
+
+/// キー割り当て画面のカーソルを片付ける関数
+fn options_teardown(mut commands: Commands) {
+    info_once!("options_teardown");
+
+    commands.remove_resource::<OptionsCursor>();
+}
+
+pub struct OptionsPlugin;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Options), options_setup)
+            .add_systems(OnExit(AppState::Options), options_teardown)
+            .add_systems(Update, (
+                options_input,
+                options_text,
+            ).chain().run_if(in_state(AppState::Options)))
+        ;
+    }
+}
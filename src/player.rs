@@ -1,7 +1,14 @@
 use std::{f32, time::Duration};
 use bevy::prelude::*;
 
-use crate::{Direction, MoveEvent, PATH_IMAGE_PLAYER};
+use crate::{
+    AppState, Direction, GameOverEvent, InGame, GoalEvent, LandEvent, MissEvent, MoveEvent,
+    GameplaySet, PATH_IMAGE_PLAYER, in_play,
+    collision::{Hitbox, HitboxKind},
+    grid::{Grid, GridPos, PlayField},
+    interpolation::Interpolated,
+    level::{Level, LevelReloadEvent},
+};
 
 const IMAGE_SIZE: UVec2 = UVec2::splat(18);
 const IMAGE_COLUMN: u32 = 8;
@@ -12,25 +19,61 @@ const IMAGE_ROW: u32 = 4;
 /// - last_sprite_index: アニメーションの最後のインデックス
 /// - frame_timer: アニメーションを行う表示速度
 #[derive(Component, Debug)]
-struct Player {
+pub struct Player {
     first_sprite_index: usize,
     last_sprite_index: usize,
     frame_timer: Timer,
 }
 
+/// プレイヤーが跳んでいる間のコンポーネント
+/// - end: 跳んだ先の位置
+/// - timer: 跳び始めてからの経過時間
+/// - curve: 跳び始めた位置から跳んだ先までのイージングカーブ
+#[derive(Component, Debug)]
+pub struct Hop {
+    end: Vec2,
+    timer: Timer,
+    curve: EasingCurve<Vec2>,
+}
+
+impl Hop {
+    pub fn new(start: Vec2, end: Vec2) -> Self {
+        Self {
+            end,
+            timer: Timer::from_seconds(Player::HOP_SECS, TimerMode::Once),
+            curve: EasingCurve::new(start, end, Player::HOP_EASING),
+        }
+    }
+}
+
+/// 残りのプレイヤー数を管理するリソース
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct Lives(pub u32);
+
+/// ミスしたプレイヤーがやられるアニメーションを再生している間のコンポーネント
+/// - 0: アニメーションが終わるまでのタイマー
 #[derive(Component, Debug, Deref, DerefMut)]
-struct Velocity(Vec2);
+pub struct Dying(pub Timer);
 
 impl Player {
     const FPS: u8 = 4;
-    const SPEED: f32 = 32.0;
-    const DISTANCE: f32 = 32.0;
+    pub const DISTANCE: f32 = 32.0;
+    const SCALE: f32 = 2.0;
+    const HOP_SECS: f32 = 0.15;
+    const HOP_EASING: EaseFunction = EaseFunction::QuadraticOut;
+    /// 跳んでいる途中に大きくなる割合
+    const HOP_ARC: f32 = 0.25;
+    pub const SIZE: Vec2 = Vec2::splat(24.0);
+    const Z: f32 = 1.0;
+    const INITIAL_LIVES: u32 = 3;
+    const DEATH_SECS: f32 = 1.0;
     const INDICES_LEFT: (usize, usize) = (8, 11);
     const INDICES_RIGHT: (usize, usize) = (16, 19);
-    const INDICES_TOP: (usize, usize) = (24, 27);
+    pub const INDICES_TOP: (usize, usize) = (24, 27);
     const INDICES_BOTTOM: (usize, usize) = (0, 3);
+    const INDICES_DEATH: (usize, usize) = (4, 7);
 
-    fn new() -> Self {
+    pub fn new() -> Self {
         let first_sprite_index = Self::INDICES_BOTTOM.0;
         let last_sprite_index = Self::INDICES_BOTTOM.1;
         let secs = 1.0 / Self::FPS as f32;
@@ -42,31 +85,82 @@ impl Player {
             frame_timer,
         }
     }
+
+    /// アニメーションを切り替える
+    fn set_animation(&mut self, sprite: &mut Sprite, indices: (usize, usize)) {
+        self.first_sprite_index = indices.0;
+        self.last_sprite_index = indices.1;
+
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            atlas.index = self.first_sprite_index;
+        }
+    }
+
+    /// スタート地点に戻す
+    fn respawn(&mut self, sprite: &mut Sprite, transform: &mut Transform, grid_pos: &mut GridPos, grid: &Grid, start: GridPos) {
+        self.set_animation(sprite, Self::INDICES_BOTTOM);
+        transform.translation = grid.to_world(start).extend(Self::Z);
+        transform.scale = Vec3::splat(Self::SCALE);
+        *grid_pos = start;
+    }
 }
 
-/// プレイヤーのセットアップを行う関数
-fn player_setup(
+/// プレイヤーの画像を保持するリソース
+/// 巣に置く目印やメスのカエルも同じ画像を使う
+#[derive(Resource, Debug)]
+pub struct PlayerAtlas {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+impl PlayerAtlas {
+    /// 画像の番号のスプライトを作る
+    pub fn sprite(&self, index: usize) -> Sprite {
+        Sprite::from_atlas_image(
+            self.texture.clone(),
+            TextureAtlas {
+                layout: self.layout.clone(),
+                index,
+            },
+        )
+    }
+}
+
+/// プレイヤーの画像を読み込む関数
+fn player_atlas_setup(
     mut commands: Commands,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     asset_server: Res<AssetServer>,
 ) {
-    info_once!("player_setup");
+    info_once!("player_atlas_setup");
 
     let texture = asset_server.load(PATH_IMAGE_PLAYER);
     let layout = TextureAtlasLayout::from_grid(IMAGE_SIZE, IMAGE_COLUMN, IMAGE_ROW, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+    let layout = texture_atlas_layouts.add(layout);
+    commands.insert_resource(PlayerAtlas { texture, layout });
+}
+
+/// プレイヤーのセットアップを行う関数
+fn player_setup(
+    mut commands: Commands,
+    atlas: Res<PlayerAtlas>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+) {
+    info_once!("player_setup");
+
+    commands.insert_resource(Lives(Player::INITIAL_LIVES));
+
+    let translation = grid.to_world(level.start).extend(Player::Z);
 
     commands.spawn((
-        Sprite::from_atlas_image(
-            texture, 
-            TextureAtlas {
-                layout: texture_atlas_layout,
-                index: 0,
-            },
-        ),
-        Transform::from_scale(Vec3::splat(2.0)),
+        atlas.sprite(0),
+        Transform::from_translation(translation).with_scale(Vec3::splat(Player::SCALE)),
+        Interpolated::new(translation),
         Player::new(),
-        Velocity(Vec2::ZERO),
+        Hitbox::new(HitboxKind::Player, Player::SIZE),
+        level.start,
+        StateScoped(InGame),
     ));
 }
 
@@ -95,7 +189,7 @@ fn player_animation(
 /// イベントを受け取り、プレイヤーのアニメーションの振る舞いを決める関数
 fn player_change_animation(
     mut events: EventReader<MoveEvent>,
-    mut query: Query<(&mut Player, &mut Sprite), With<Player>>,
+    mut query: Query<(&mut Player, &mut Sprite), Without<Dying>>,
 ) {
     info_once!("player_change_animation");
 
@@ -133,65 +227,185 @@ fn player_change_animation(
     }
 }
 
-/// プレイヤーの移動を管理する関数
+/// 入力を受け取り、プレイヤーを跳ばせる関数
+/// 升目の位置は跳び始めたときに更新する
+#[allow(clippy::type_complexity)]
 fn player_movement(
+    mut commands: Commands,
     mut events: EventReader<MoveEvent>,
-    mut query: Query<(&mut Transform, &mut Velocity), With<Player>>,
+    mut query: Query<(Entity, &mut GridPos, &Transform, Has<Hop>), (With<Player>, Without<Dying>)>,
+    grid: Res<Grid>,
+    play_field: Res<PlayField>,
 ) {
     info_once!("player_movement");
 
-    // プレイヤーの値を取得。プレイヤーがなければ処理を抜ける
-    let Ok((mut transform, mut velocity)) = query.get_single_mut() else {
+    // プレイヤーの値を取得。プレイヤーがいない、またはやられている途中なら処理を抜ける
+    let Ok((entity, mut grid_pos, transform, hopping)) = query.get_single_mut() else {
+        events.clear();
         return;
     };
-    let x = transform.translation.x;
-    let y = transform.translation.y;
-    let dist = Player::DISTANCE;
 
-    // x座標がDISTANCEの倍数付近なら丸めて停止
-    if (x % dist).abs() < 1.0 {
-        velocity.x = 0.0;
-        transform.translation.x = (x / dist).round() * dist;
+    // 跳んでいる途中は入力を受け付けない
+    if hopping {
+        events.clear();
+        return;
     }
-    // y座標がDISTANCEの倍数付近なら丸めて停止
-    if (y % dist).abs() < 1.0 {
-        velocity.y = 0.0;
-        transform.translation.y = (y / dist).round() * dist;
+
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let step = event.step();
+
+    // 動ける範囲の外には跳ばない
+    let target = grid_pos.offset(step);
+    if !play_field.contains(target) {
+        return;
     }
 
-    for event in events.read() {
-        match **event {
-            Direction::Left => {
-                transform.translation.x -= 1.0;
-                velocity.x = -Player::SPEED;
-            }
-            Direction::Right => {
-                transform.translation.x += 1.0;
-                velocity.x = Player::SPEED;
-            }
-            Direction::Top => {
-                transform.translation.y += 1.0;
-                velocity.y = Player::SPEED;
-            }
-            Direction::Bottom => {
-                transform.translation.y -= 1.0;
-                velocity.y = -Player::SPEED;
-            }
-        }
+    // 丸木に乗っている間はx座標が升目からずれているため、今の位置から1マス先を目指す
+    let start = transform.translation.truncate();
+    let end = start + step.as_vec2() * grid.cell_size;
+    *grid_pos = target;
+    commands.entity(entity).insert(Hop::new(start, end));
+}
+
+/// 跳んでいるプレイヤーを一定時間で跳んだ先まで動かす関数
+#[allow(clippy::type_complexity)]
+fn player_hop(
+    mut commands: Commands,
+    mut events: EventWriter<LandEvent>,
+    mut query: Query<(Entity, &mut Hop, &mut Transform, &GridPos), (With<Player>, Without<Dying>)>,
+    time: Res<Time>,
+) {
+    info_once!("player_hop");
+
+    let Ok((entity, mut hop, mut transform, grid_pos)) = query.get_single_mut() else {
+        return;
+    };
+
+    hop.timer.tick(time.delta());
+    let t = hop.timer.fraction();
+    let position = hop.curve.sample_clamped(t);
+    // 跳んでいる途中は少し大きくして、弧を描いて跳んでいるように見せる
+    let arc = (t * f32::consts::PI).sin() * Player::HOP_ARC;
+
+    transform.translation = position.extend(transform.translation.z);
+    transform.scale = Vec3::new(1.0 + arc, 1.0 + arc * 0.5, 1.0) * Player::SCALE;
+
+    // 跳び終わったら跳んだ先にぴったり合わせ、着地した行を知らせる
+    if hop.timer.finished() {
+        transform.translation = hop.end.extend(transform.translation.z);
+        transform.scale = Vec3::splat(Player::SCALE);
+        commands.entity(entity).remove::<Hop>();
+        events.send(LandEvent(grid_pos.row));
     }
 }
 
-/// 速度に応じてコンポーネントを移動する関数
-fn apply_velocity(
-    mut query: Query<(&mut Transform, &Velocity), With<Velocity>>,
-    time_step: Res<Time<Fixed>>,
+/// ゴールしたプレイヤーをスタート地点に戻す関数
+fn player_goal(
+    mut events: EventReader<GoalEvent>,
+    mut query: Query<(&mut Player, &mut Sprite, &mut Transform, &mut GridPos)>,
+    grid: Res<Grid>,
+    level: Res<Level>,
 ) {
-    info_once!("apply_velocity");
+    info_once!("player_goal");
 
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * time_step.delta().as_secs_f32();
-        transform.translation.y += velocity.y * time_step.delta().as_secs_f32();
+    // 同じフレームで複数回イベントを受け取っても一度だけ処理する
+    if events.is_empty() {
+        return;
     }
+    events.clear();
+
+    let Ok((mut player, mut sprite, mut transform, mut grid_pos)) = query.get_single_mut() else {
+        return;
+    };
+
+    player.respawn(&mut sprite, &mut transform, &mut grid_pos, &grid, level.start);
+}
+
+/// ステージを読み込み直したらプレイヤーを新しいスタート地点に戻す関数
+fn player_reload(
+    mut commands: Commands,
+    mut events: EventReader<LevelReloadEvent>,
+    mut query: Query<(Entity, &mut Player, &mut Sprite, &mut Transform, &mut GridPos), Without<Dying>>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+) {
+    info_once!("player_reload");
+
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    let Ok((entity, mut player, mut sprite, mut transform, mut grid_pos)) = query.get_single_mut() else {
+        return;
+    };
+
+    player.respawn(&mut sprite, &mut transform, &mut grid_pos, &grid, level.start);
+    // Updateで動かしたので、補間の位置も合わせないと次の固定タイムステップで元の位置に戻される
+    commands.entity(entity).remove::<Hop>().insert(Interpolated::new(transform.translation));
+}
+
+/// ミスしたプレイヤーの残り数を減らし、やられるアニメーションを開始する関数
+fn player_miss(
+    mut commands: Commands,
+    mut events: EventReader<MissEvent>,
+    mut query: Query<(Entity, &mut Player, &mut Sprite, &mut Transform), Without<Dying>>,
+    mut lives: ResMut<Lives>,
+) {
+    info_once!("player_miss");
+
+    // 同じフレームで複数回ミスしても一度だけ処理する
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    // やられている途中のプレイヤーは対象外
+    let Ok((entity, mut player, mut sprite, mut transform)) = query.get_single_mut() else {
+        return;
+    };
+
+    **lives = lives.saturating_sub(1);
+    transform.scale = Vec3::splat(Player::SCALE);
+    player.set_animation(&mut sprite, Player::INDICES_DEATH);
+
+    // 跳んでいる途中でもその場で止める
+    let timer = Timer::from_seconds(Player::DEATH_SECS, TimerMode::Once);
+    commands.entity(entity).remove::<Hop>().insert(Dying(timer));
+}
+
+/// やられるアニメーションが終わったら、スタート地点に戻すかゲームオーバーにする関数
+fn player_dying(
+    mut commands: Commands,
+    mut events: EventWriter<GameOverEvent>,
+    mut query: Query<(Entity, &mut Player, &mut Dying, &mut Sprite, &mut Transform, &mut GridPos)>,
+    lives: Res<Lives>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    time: Res<Time>,
+) {
+    info_once!("player_dying");
+
+    let Ok((entity, mut player, mut dying, mut sprite, mut transform, mut grid_pos)) = query.get_single_mut() else {
+        return;
+    };
+
+    // 終わったタイムステップだけ処理する。ゲームオーバーのときはDyingを残したままにするので、
+    // ステートが変わるまでの間にイベントを何度も送らないようにする
+    if !dying.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // 残りがなければゲームオーバー
+    if **lives == 0 {
+        events.send_default();
+        return;
+    }
+
+    commands.entity(entity).remove::<Dying>();
+    player.respawn(&mut sprite, &mut transform, &mut grid_pos, &grid, level.start);
 }
 
 pub struct PlayerPlugin;
@@ -199,13 +413,24 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Startup, player_setup)
+            .insert_resource(Lives(Player::INITIAL_LIVES))
+            .add_systems(Startup, player_atlas_setup)
+            .add_systems(OnEnter(InGame), player_setup)
+            .add_systems(Update, player_reload.run_if(in_state(InGame)))
             .add_systems(Update, (
-                player_animation,
                 player_change_animation,
+                player_animation,
+            ).chain().in_set(GameplaySet::Animation).run_if(in_state(AppState::Playing)))
+            .add_systems(FixedUpdate, (
                 player_movement,
-                apply_velocity,
-            ))
+                player_hop,
+                player_dying,
+            ).chain().in_set(GameplaySet::Movement).run_if(in_play))
+            // ゴールやミスは、判定した固定タイムステップのうちにプレイヤーを戻し、次の判定に残さない
+            .add_systems(FixedPostUpdate, (
+                player_goal,
+                player_miss,
+            ).run_if(in_state(InGame)))
         ;
     }
 }
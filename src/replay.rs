@@ -0,0 +1,383 @@
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Direction, GameplaySet, InGame, MoveEvent, in_play,
+};
+
+/// 再生するリプレイのファイルを指定するコマンドライン引数
+const ARG_REPLAY: &str = "--replay";
+/// リプレイを保存するディレクトリの名前
+const DATA_DIR: &str = "ittoku-frogger";
+const REPLAY_DIR: &str = "replays";
+/// 最後に遊んだゲームのリプレイのファイルの名前
+const REPLAY_FILE_LAST: &str = "last.ron";
+
+/// 移動イベント1つ分の記録
+/// - tick: ゲームが始まってから何回目の固定タイムステップで送られたか
+/// - direction: 移動の向き
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReplayMove {
+    pub tick: u64,
+    pub direction: Direction,
+}
+
+/// リプレイのファイルの内容
+/// - seed: 乱数の種
+/// - moves: 送られた順の移動イベント
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayData {
+    pub seed: u64,
+    pub moves: Vec<ReplayMove>,
+}
+
+/// リプレイの記録と再生を管理するリソース
+/// - data: 記録中、または再生中のリプレイ
+/// - playing: 入力の代わりにリプレイを再生しているか
+/// - cursor: 次に再生する移動イベントの番号
+#[derive(Resource, Debug, Default)]
+pub struct Replay {
+    pub data: ReplayData,
+    pub playing: bool,
+    cursor: usize,
+}
+
+/// 最後に遊んだゲームのリプレイを保存するファイルの場所を管理するリソース
+/// - 0: ファイルの場所。データのディレクトリが分からないときはNoneにして、保存しない
+#[derive(Resource, Debug, Deref)]
+pub struct ReplayPath(pub Option<PathBuf>);
+
+impl Default for ReplayPath {
+    fn default() -> Self {
+        Self(dirs::data_dir().map(|dir| dir.join(DATA_DIR).join(REPLAY_DIR).join(REPLAY_FILE_LAST)))
+    }
+}
+
+/// ゲームが始まってから進んだ固定タイムステップの回数を管理するリソース
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct FixedTick(pub u64);
+
+/// 乱数の種から作った、ゲームで使う乱数を管理するリソース
+/// 同じ種なら同じ順で同じ値を返すので、リプレイでも同じ結果になる
+/// StdRngはバージョンによって中身が変わり、保存したリプレイが再現できなくなるため、アルゴリズムが決まっているものを使う
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct GameRng(ChaCha8Rng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(ChaCha8Rng::seed_from_u64(0))
+    }
+}
+
+impl ReplayData {
+    /// ファイルから読み込む
+    fn load(path: &Path) -> Option<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("failed to read {}: {}", path.display(), err);
+                return None;
+            }
+        };
+
+        match ron::from_str(&text) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                warn!("failed to parse {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// ファイルに書き込む
+    fn save(&self, path: &Path) {
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("failed to serialize replay: {}", err);
+                return;
+            }
+        };
+        let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path, text));
+
+        if let Err(err) = result {
+            warn!("failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// 入力の代わりにリプレイを再生しているかを返す関数
+pub fn replaying(replay: Res<Replay>) -> bool {
+    replay.playing
+}
+
+/// コマンドライン引数でリプレイが指定されていれば読み込む関数
+fn replay_load(mut replay: ResMut<Replay>) {
+    info_once!("replay_load");
+
+    let mut args = env::args().skip_while(|arg| arg != ARG_REPLAY).skip(1);
+    let Some(path) = args.next() else {
+        return;
+    };
+    let Some(data) = ReplayData::load(Path::new(&path)) else {
+        return;
+    };
+
+    info!("replay: {} ({} moves)", path, data.moves.len());
+    replay.data = data;
+    replay.playing = true;
+}
+
+/// ゲームを始めるときに、記録を始める、または再生を最初からやり直す関数
+fn replay_setup(
+    mut replay: ResMut<Replay>,
+    mut tick: ResMut<FixedTick>,
+    mut rng: ResMut<GameRng>,
+) {
+    info_once!("replay_setup");
+
+    **tick = 0;
+    replay.cursor = 0;
+    if !replay.playing {
+        replay.data = ReplayData {
+            seed: rand::random(),
+            moves: Vec::new(),
+        };
+    }
+    *rng = GameRng(ChaCha8Rng::seed_from_u64(replay.data.seed));
+}
+
+/// 固定タイムステップの回数を数える関数
+fn replay_tick(mut tick: ResMut<FixedTick>) {
+    info_once!("replay_tick");
+
+    **tick += 1;
+}
+
+/// 送られた移動イベントを、固定タイムステップの回数と一緒に記録する関数
+fn replay_record(
+    mut events: EventReader<MoveEvent>,
+    mut replay: ResMut<Replay>,
+    tick: Res<FixedTick>,
+) {
+    info_once!("replay_record");
+
+    for event in events.read() {
+        replay.data.moves.push(ReplayMove { tick: **tick, direction: **event });
+    }
+}
+
+/// 記録した固定タイムステップの回数になったら、移動イベントを送る関数
+fn replay_play(
+    mut events: EventWriter<MoveEvent>,
+    mut replay: ResMut<Replay>,
+    tick: Res<FixedTick>,
+) {
+    info_once!("replay_play");
+
+    while let Some(replay_move) = replay.data.moves.get(replay.cursor).copied() {
+        if replay_move.tick > **tick {
+            break;
+        }
+        events.send(MoveEvent(replay_move.direction));
+        replay.cursor += 1;
+    }
+}
+
+/// ゲームが終わったら、記録したリプレイを保存する関数
+fn replay_save(replay: Res<Replay>, path: Res<ReplayPath>) {
+    info_once!("replay_save");
+
+    if replay.playing {
+        return;
+    }
+    if let Some(path) = path.as_deref() {
+        replay.data.save(path);
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Replay>()
+            .init_resource::<FixedTick>()
+            .init_resource::<ReplayPath>()
+            .init_resource::<GameRng>()
+            .add_systems(Startup, replay_load)
+            .add_systems(OnEnter(InGame), replay_setup)
+            .add_systems(OnExit(InGame), replay_save)
+            .add_systems(FixedFirst, replay_tick.run_if(in_play))
+            .add_systems(FixedUpdate, (
+                replay_play.in_set(GameplaySet::Input).run_if(replaying),
+                replay_record.after(GameplaySet::Input).run_if(not(replaying)),
+            ).run_if(in_play))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        AppState,
+        bonus::Fly,
+        grid::GridPos,
+        key::InputBuffer,
+        player::{Lives, Player},
+        testing,
+    };
+
+    /// 記録するときに上へ移動する間隔のフレーム数
+    const MOVE_INTERVAL: u32 = 40;
+    /// 記録して再生するフレーム数。ハエが何回か現れるだけの長さにする
+    const FRAMES: u32 = 900;
+    /// ステージを読み込んでからタイトルになるまでに待つ最大のフレーム数
+    const TITLE_FRAMES: u32 = 10;
+
+    /// 固定タイムステップごとのゲームの様子
+    /// 乱数やハエの巣は種によって変わるので、再生で同じになれば種も再現できている
+    #[derive(Debug, Clone, PartialEq)]
+    struct Snapshot {
+        tick: u64,
+        player: Option<GridPos>,
+        lives: u32,
+        rng: ChaCha8Rng,
+        flies: Vec<usize>,
+    }
+
+    /// テストで記録するもの
+    /// - moves: 移動イベントが送られた固定タイムステップの回数と向き
+    /// - snapshots: 固定タイムステップごとのゲームの様子
+    #[derive(Resource, Debug, Default)]
+    struct Trace {
+        moves: Vec<(u64, Direction)>,
+        snapshots: Vec<Snapshot>,
+    }
+
+    fn trace_moves(
+        mut events: EventReader<MoveEvent>,
+        mut trace: ResMut<Trace>,
+        tick: Res<FixedTick>,
+    ) {
+        for event in events.read() {
+            trace.moves.push((**tick, **event));
+        }
+    }
+
+    fn trace_snapshot(
+        mut trace: ResMut<Trace>,
+        tick: Res<FixedTick>,
+        rng: Res<GameRng>,
+        lives: Res<Lives>,
+        player_query: Query<&GridPos, With<Player>>,
+        fly_query: Query<&Fly>,
+    ) {
+        let mut flies: Vec<usize> = fly_query.iter().map(|fly| fly.home).collect();
+        flies.sort();
+        trace.snapshots.push(Snapshot {
+            tick: **tick,
+            player: player_query.get_single().ok().copied(),
+            lives: **lives,
+            rng: (**rng).clone(),
+            flies,
+        });
+    }
+
+    /// ゲームを動かすアプリに記録用のシステムを足し、タイトルからゲームを始める
+    fn start(mut app: App) -> App {
+        app
+            .init_resource::<Trace>()
+            .add_systems(FixedUpdate, (
+                trace_moves.after(GameplaySet::Input),
+                trace_snapshot.after(GameplaySet::Collision),
+            ).run_if(in_play));
+
+        for _ in 0..TITLE_FRAMES {
+            app.update();
+            if *app.world().resource::<State<AppState>>().get() == AppState::Title {
+                app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Playing);
+                return app;
+            }
+        }
+        panic!("game did not reach the title");
+    }
+
+    /// ゲームを終えてタイトルに戻す
+    fn finish(app: &mut App) {
+        if *app.world().resource::<State<AppState>>().get() == AppState::Playing {
+            app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Title);
+            app.update();
+        }
+    }
+
+    #[test]
+    fn replay_data_round_trips_through_ron() {
+        let data = ReplayData {
+            seed: 0x0123_4567_89ab_cdef,
+            moves: vec![
+                ReplayMove { tick: 3, direction: Direction::Top },
+                ReplayMove { tick: 40, direction: Direction::Left },
+                ReplayMove { tick: 41, direction: Direction::Bottom },
+            ],
+        };
+
+        let text = ron::ser::to_string_pretty(&data, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded: ReplayData = ron::from_str(&text).unwrap();
+
+        assert_eq!(loaded.seed, data.seed);
+        assert_eq!(loaded.moves.len(), data.moves.len());
+        for (loaded, original) in loaded.moves.iter().zip(&data.moves) {
+            assert_eq!(loaded.tick, original.tick);
+            assert_eq!(loaded.direction, original.direction);
+        }
+    }
+
+    #[test]
+    fn saved_replay_reproduces_the_game() {
+        let dir = testing::temp_dir("replay");
+
+        let mut app = start(testing::game_app(&dir));
+        for frame in 1..=FRAMES {
+            if frame % MOVE_INTERVAL == 0 {
+                app.world_mut().resource_mut::<InputBuffer>().push(Direction::Top);
+            }
+            app.update();
+        }
+        finish(&mut app);
+        let recorded = app.world_mut().remove_resource::<Trace>().unwrap();
+
+        let data = ReplayData::load(&dir.join(REPLAY_FILE_LAST)).expect("replay was not saved");
+        let start_row = recorded.snapshots[0].player.unwrap().row;
+        assert!(recorded.snapshots.iter().any(|snapshot| snapshot.player.is_some_and(|pos| pos.row != start_row)));
+        assert!(recorded.snapshots.iter().any(|snapshot| !snapshot.flies.is_empty()));
+        assert_eq!(
+            data.moves.iter().map(|replay_move| (replay_move.tick, replay_move.direction)).collect::<Vec<_>>(),
+            recorded.moves,
+        );
+
+        let mut app = testing::game_app(&dir);
+        app.insert_resource(Replay { data, playing: true, cursor: 0 });
+        let mut app = start(app);
+        for _ in 0..FRAMES {
+            app.update();
+        }
+        finish(&mut app);
+        let replayed = app.world_mut().remove_resource::<Trace>().unwrap();
+
+        assert_eq!(replayed.moves, recorded.moves);
+        assert_eq!(replayed.snapshots, recorded.snapshots);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
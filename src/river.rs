@@ -0,0 +1,435 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    AppState, GameplaySet, InGame, MissEvent, Velocity, WINDOW_SIZE, apply_velocity, in_play,
+    collision::{CollisionEvent, Hitbox, HitboxKind, collision_broadphase},
+    difficulty::Difficulty,
+    grid::{Grid, GridPos, PlayField},
+    interpolation::Interpolated,
+    level::{Level, LevelReloadEvent, RowKind},
+    player::{Hop, Player},
+};
+
+/// 画面外で足場が折り返すまでの余白
+const WRAP_MARGIN: f32 = 128.0;
+const COLOR_WATER: Color = Color::srgb(0.0, 0.1, 0.4);
+const COLOR_LOG: Color = Color::srgb(0.5, 0.3, 0.1);
+const COLOR_TURTLE: Color = Color::srgb(0.2, 0.6, 0.3);
+const COLOR_CROCODILE: Color = Color::srgb(0.1, 0.35, 0.1);
+const COLOR_CROCODILE_JAWS: Color = Color::srgb(0.8, 0.1, 0.1);
+const COLOR_TURTLE_WARNING: Color = Color::srgb(0.5, 0.8, 0.5);
+const COLOR_TURTLE_SUBMERGED: Color = Color::srgba(0.2, 0.6, 0.3, 0.2);
+/// 潜りかけの亀が点滅する間隔（秒）
+const WARNING_BLINK_SECS: f32 = 0.125;
+/// 周期を指定していない亀が潜る周期（秒）
+const DEFAULT_DIVE_CYCLE: DiveCycle = DiveCycle {
+    surface: 5.0,
+    warning: 1.0,
+    submerged: 1.5,
+    resurface: 0.5,
+};
+
+/// 配置し直すときに消す、川と足場を探すクエリ
+type RiverQuery<'w, 's> = Query<'w, 's, Entity, Or<(With<Platform>, With<Water>)>>;
+
+/// 川を流れる足場の種類
+/// - 0: 足場の長さ（マス）
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PlatformKind {
+    Log(u8),
+    Turtles(u8),
+    /// 背中には乗れるが、口に当たるとミスになる
+    Crocodile(u8),
+}
+
+impl PlatformKind {
+    fn size(&self) -> Vec2 {
+        match self {
+            PlatformKind::Log(len) | PlatformKind::Turtles(len) | PlatformKind::Crocodile(len) => {
+                Vec2::new(Player::DISTANCE * *len as f32, 24.0)
+            }
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            PlatformKind::Log(_) => COLOR_LOG,
+            PlatformKind::Turtles(_) => COLOR_TURTLE,
+            PlatformKind::Crocodile(_) => COLOR_CROCODILE,
+        }
+    }
+}
+
+/// 亀が潜る周期（秒）
+/// - surface: 水面にいる時間
+/// - warning: 潜りかけの時間。点滅して知らせる
+/// - submerged: 潜っている時間。この間は乗れない
+/// - resurface: 浮かびかけの時間。この間は乗れる
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct DiveCycle {
+    pub surface: f32,
+    pub warning: f32,
+    pub submerged: f32,
+    pub resurface: f32,
+}
+
+impl Default for DiveCycle {
+    fn default() -> Self {
+        DEFAULT_DIVE_CYCLE
+    }
+}
+
+impl DiveCycle {
+    /// 段階の長さを返す
+    fn secs(&self, phase: DivePhase) -> f32 {
+        match phase {
+            DivePhase::Surface => self.surface,
+            DivePhase::Warning => self.warning,
+            DivePhase::Submerged => self.submerged,
+            DivePhase::Resurface => self.resurface,
+        }
+    }
+}
+
+/// 亀が潜る周期の段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DivePhase {
+    Surface,
+    Warning,
+    Submerged,
+    Resurface,
+}
+
+impl DivePhase {
+    fn next(&self) -> Self {
+        match self {
+            DivePhase::Surface => DivePhase::Warning,
+            DivePhase::Warning => DivePhase::Submerged,
+            DivePhase::Submerged => DivePhase::Resurface,
+            DivePhase::Resurface => DivePhase::Surface,
+        }
+    }
+}
+
+/// 周期的に潜る亀のコンポーネント
+/// - cycle: 潜る周期
+/// - phase: 今の段階
+/// - timer: 今の段階を終えるまでのタイマー
+#[derive(Component, Debug)]
+struct Dive {
+    cycle: DiveCycle,
+    phase: DivePhase,
+    timer: Timer,
+}
+
+impl Dive {
+    fn new(cycle: DiveCycle) -> Self {
+        Self {
+            cycle,
+            phase: DivePhase::Surface,
+            timer: Timer::from_seconds(cycle.surface, TimerMode::Once),
+        }
+    }
+
+    /// 次の段階に進める
+    fn advance(&mut self) {
+        self.phase = self.phase.next();
+        self.timer = Timer::from_seconds(self.cycle.secs(self.phase), TimerMode::Once);
+    }
+
+    /// 潜っていて乗れないか
+    fn submerged(&self) -> bool {
+        self.phase == DivePhase::Submerged
+    }
+}
+
+/// 川を流れる足場のコンポーネント
+#[derive(Component, Debug)]
+struct Platform;
+
+/// 丸太の足場のコンポーネント
+/// - len: 丸太の長さ（マス）
+#[derive(Component, Debug)]
+pub struct Log {
+    pub len: u8,
+}
+
+impl Log {
+    /// 丸太の幅を返す
+    pub fn width(&self) -> f32 {
+        PlatformKind::Log(self.len).size().x
+    }
+}
+
+/// 川の水面のコンポーネント
+#[derive(Component, Debug)]
+struct Water;
+
+/// プレイヤーが足場に乗っていることを表すコンポーネント
+#[derive(Component, Debug, Deref)]
+struct Riding(Entity);
+
+/// ステージの川の行に水面と、難易度に合わせて足場を配置する
+fn spawn_river(commands: &mut Commands, grid: &Grid, level: &Level, difficulty: &Difficulty) {
+    let span = WINDOW_SIZE.x + WRAP_MARGIN * 2.0;
+
+    for (row, kind) in level.iter_rows() {
+        let RowKind::River { lane, platform, dive } = kind else {
+            continue;
+        };
+        let y = grid.to_world(GridPos::new(0, row)).y;
+
+        // 水面
+        commands.spawn((
+            Sprite::from_color(COLOR_WATER, Vec2::new(WINDOW_SIZE.x, grid.cell_size)),
+            Transform::from_xyz(0.0, y, -1.0),
+            GridPos::new(grid.cols / 2, row),
+            Water,
+            Hitbox::new(HitboxKind::Water, Vec2::new(WINDOW_SIZE.x, grid.cell_size)),
+            StateScoped(InGame),
+        ));
+
+        // 足場
+        let lane = difficulty.river_lane(lane);
+        let platform = difficulty.platform(*platform);
+        let size = platform.size();
+        // プレイヤーの中心が足場の上にあるときだけ重なるよう、プレイヤーの幅だけ狭くする
+        let hitbox_size = Vec2::new(size.x - Player::SIZE.x, size.y);
+        let (count, spacing) = lane.layout(span);
+        // 潜るのは亀だけ
+        let dive = match platform {
+            PlatformKind::Turtles(_) => difficulty.dive(*dive),
+            PlatformKind::Log(_) | PlatformKind::Crocodile(_) => None,
+        };
+
+        for i in 0..count {
+            let position = Vec2::new(-span / 2.0 + spacing * i as f32, y);
+            let mut entity = commands.spawn((
+                Sprite::from_color(platform.color(), size),
+                Transform::from_translation(position.extend(0.0)),
+                Interpolated::new(position.extend(0.0)),
+                grid.to_grid(position),
+                Platform,
+                Hitbox::new(HitboxKind::Platform, hitbox_size),
+                Velocity(lane.velocity()),
+                StateScoped(InGame),
+            ));
+            if let Some(cycle) = dive {
+                entity.insert(Dive::new(cycle));
+            }
+            match platform {
+                PlatformKind::Log(len) => {
+                    entity.insert(Log { len });
+                }
+                PlatformKind::Crocodile(_) => {
+                    // 進む向きの先頭の1マスを口にする
+                    let jaws_size = Vec2::new(Player::DISTANCE.min(size.x), size.y);
+                    let jaws_x = (size.x - jaws_size.x) / 2.0 * lane.velocity().x.signum();
+                    entity.with_child((
+                        Sprite::from_color(COLOR_CROCODILE_JAWS, jaws_size),
+                        Transform::from_xyz(jaws_x, 0.0, 0.1),
+                        Hitbox::new(HitboxKind::Enemy, jaws_size),
+                    ));
+                }
+                PlatformKind::Turtles(_) => {}
+            }
+        }
+    }
+}
+
+/// 配置してある水面と足場を消し、今のステージと難易度で配置し直す
+/// 足場に乗っているものも一緒に消す
+fn respawn_river(
+    commands: &mut Commands,
+    query: &RiverQuery,
+    grid: &Grid,
+    level: &Level,
+    difficulty: &Difficulty,
+) {
+    for entity in query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_river(commands, grid, level, difficulty);
+}
+
+/// 川と足場を配置する関数
+fn river_setup(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("river_setup");
+
+    spawn_river(&mut commands, &grid, &level, &difficulty);
+}
+
+/// ステージを読み込み直したら川と足場を配置し直す関数
+fn river_reload(
+    mut commands: Commands,
+    mut events: EventReader<LevelReloadEvent>,
+    query: RiverQuery,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("river_reload");
+
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    respawn_river(&mut commands, &query, &grid, &level, &difficulty);
+}
+
+/// 次のラウンドを始めるときに、新しい難易度で川と足場を配置し直す関数
+fn river_next_round(
+    mut commands: Commands,
+    query: RiverQuery,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("river_next_round");
+
+    respawn_river(&mut commands, &query, &grid, &level, &difficulty);
+}
+
+/// 画面外に出た足場を反対側に戻す関数
+fn river_wrap(mut query: Query<&mut Transform, With<Platform>>) {
+    info_once!("river_wrap");
+
+    let span = WINDOW_SIZE.x + WRAP_MARGIN * 2.0;
+
+    for mut transform in &mut query {
+        let x = transform.translation.x;
+
+        if x > span / 2.0 {
+            transform.translation.x -= span;
+        } else if x < -span / 2.0 {
+            transform.translation.x += span;
+        }
+    }
+}
+
+/// 固定タイムステップで亀が潜る周期を進める関数
+/// 潜っている間は当たり判定を無効にする
+fn river_dive(
+    mut query: Query<(&mut Dive, &mut Hitbox)>,
+    time: Res<Time>,
+) {
+    info_once!("river_dive");
+
+    for (mut dive, mut hitbox) in &mut query {
+        if dive.timer.tick(time.delta()).finished() {
+            dive.advance();
+        }
+        hitbox.enabled = !dive.submerged();
+    }
+}
+
+/// 潜る段階に合わせて亀の色を変える関数
+/// 潜りかけのときは点滅させる
+#[allow(clippy::manual_is_multiple_of)]
+fn river_dive_sprite(mut query: Query<(&mut Sprite, &Dive)>) {
+    info_once!("river_dive_sprite");
+
+    for (mut sprite, dive) in &mut query {
+        sprite.color = match dive.phase {
+            DivePhase::Surface => COLOR_TURTLE,
+            DivePhase::Warning => {
+                let blink = (dive.timer.elapsed_secs() / WARNING_BLINK_SECS) as u32;
+                if blink % 2 == 0 { COLOR_TURTLE } else { COLOR_TURTLE_WARNING }
+            }
+            DivePhase::Submerged => COLOR_TURTLE_SUBMERGED,
+            DivePhase::Resurface => COLOR_TURTLE_WARNING,
+        };
+    }
+}
+
+/// プレイヤーを足場に乗せて運ぶ関数
+/// 足場のない水面にいたら、または画面外に運ばれたらミスとする
+/// 潜っている亀は当たり判定が無効なので、乗っていた亀が潜ると水面にいることになる
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn river_ride(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut events: EventWriter<MissEvent>,
+    mut player_query: Query<(Entity, &mut Transform, &mut GridPos, Has<Hop>, Option<&Riding>), With<Player>>,
+    platform_query: Query<&Velocity, With<Platform>>,
+    grid: Res<Grid>,
+    play_field: Res<PlayField>,
+    time_step: Res<Time<Fixed>>,
+) {
+    info_once!("river_ride");
+
+    // 跳んでいる間に届いた水や足場のイベントも、ここで読み捨てる
+    let collisions: Vec<_> = collision_events.read().copied().collect();
+
+    let Ok((entity, mut transform, mut grid_pos, hopping, riding)) = player_query.get_single_mut() else {
+        return;
+    };
+    // 跳んでいる間は足場に運ばれず、水にも落ちない
+    if hopping {
+        return;
+    }
+
+    if collisions.contains(&CollisionEvent::PlayerInWater) {
+        events.send_default();
+        commands.entity(entity).remove::<Riding>();
+        return;
+    }
+
+    let platform = collisions.iter().find_map(|event| match event {
+        CollisionEvent::PlayerOnPlatform(entity) => {
+            platform_query.get(*entity).ok().map(|velocity| (*entity, velocity))
+        }
+        _ => None,
+    });
+
+    // 川から陸に上がったら、x座標を升目に合わせる
+    let Some((platform_entity, platform_velocity)) = platform else {
+        if riding.is_some() {
+            transform.translation.x = grid.to_world(*grid_pos).x;
+            commands.entity(entity).remove::<Riding>();
+        }
+        return;
+    };
+
+    transform.translation.x += platform_velocity.x * time_step.delta().as_secs_f32();
+    grid_pos.col = grid.to_grid(transform.translation.truncate()).col;
+    if riding.is_none_or(|riding| **riding != platform_entity) {
+        commands.entity(entity).insert(Riding(platform_entity));
+    }
+
+    // 動ける範囲の外まで運ばれたときの扱い
+    if !play_field.contains(*grid_pos) {
+        if play_field.off_screen_death {
+            events.send_default();
+            commands.entity(entity).remove::<Riding>();
+        } else {
+            grid_pos.col = play_field.clamp_col(grid_pos.col);
+            transform.translation.x = grid.to_world(*grid_pos).x;
+        }
+    }
+}
+
+pub struct RiverPlugin;
+
+impl Plugin for RiverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(InGame), river_setup)
+            .add_systems(OnExit(AppState::RoundClear), river_next_round)
+            .add_systems(Update, river_reload.run_if(in_state(InGame)))
+            .add_systems(FixedUpdate, (
+                river_wrap.in_set(GameplaySet::Physics).after(apply_velocity),
+                river_dive.in_set(GameplaySet::Physics),
+                river_ride.in_set(GameplaySet::Collision).after(collision_broadphase),
+            ).run_if(in_play))
+            .add_systems(Update, river_dive_sprite.in_set(GameplaySet::Animation).run_if(in_state(InGame)))
+        ;
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameplaySet, GoalEvent, InGame, LandEvent, MissEvent, ScoreEvent,
+    goal::Round,
+    level::Level,
+    player::Lives,
+};
+
+/// 得点の規則
+const POINTS_STEP: u32 = 10;
+const POINTS_HOME: u32 = 50;
+const POINTS_TIME_BONUS: u32 = 10;
+const POINTS_ROUND_CLEAR: u32 = 1000;
+const POINTS_FLY: u32 = 200;
+const POINTS_LADY_FROG: u32 = 200;
+const FONT_SIZE: f32 = 18.0;
+const COLOR_LABEL: Color = Color::srgb(0.9, 0.9, 0.9);
+const COLOR_VALUE: Color = Color::srgb(1.0, 0.9, 0.2);
+
+/// 現在の得点を管理するリソース
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct Score(pub u32);
+
+/// 最高得点を管理するリソース
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct HighScore(pub u32);
+
+/// 今回のプレイヤーがこれまでに到達した一番先の行を管理するリソース
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+struct FurthestRow(i32);
+
+impl FurthestRow {
+    fn start(level: &Level) -> Self {
+        Self(level.start.row)
+    }
+}
+
+/// HUDに表示する値の種類
+#[derive(Component, Debug)]
+enum HudText {
+    Score,
+    HighScore,
+    Lives,
+    Round,
+}
+
+/// 得点の種類から加算する点数を求める関数
+fn points(event: &ScoreEvent) -> u32 {
+    match event {
+        ScoreEvent::Step => POINTS_STEP,
+        ScoreEvent::Home => POINTS_HOME,
+        ScoreEvent::TimeBonus(half_seconds) => POINTS_TIME_BONUS * half_seconds,
+        ScoreEvent::RoundClear => POINTS_ROUND_CLEAR,
+        ScoreEvent::Fly => POINTS_FLY,
+        ScoreEvent::LadyFrog => POINTS_LADY_FROG,
+    }
+}
+
+/// HUDのセットアップを行う関数
+fn score_setup(mut commands: Commands) {
+    info_once!("score_setup");
+
+    let font = TextFont::from_font_size(FONT_SIZE);
+
+    // 得点
+    commands.spawn((
+        Text::new("SCORE "),
+        font.clone(),
+        TextColor(COLOR_LABEL),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            top: Val::Px(6.0),
+            ..Default::default()
+        },
+    ))
+    .with_child((TextSpan::default(), font.clone(), TextColor(COLOR_VALUE), HudText::Score));
+
+    // 最高得点
+    commands.spawn((
+        Text::new("HI-SCORE "),
+        font.clone(),
+        TextColor(COLOR_LABEL),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(8.0),
+            top: Val::Px(6.0),
+            ..Default::default()
+        },
+    ))
+    .with_child((TextSpan::default(), font.clone(), TextColor(COLOR_VALUE), HudText::HighScore));
+
+    // 残りのプレイヤー数とラウンド数
+    commands.spawn((
+        Text::new("LIVES "),
+        font.clone(),
+        TextColor(COLOR_LABEL),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            bottom: Val::Px(6.0),
+            ..Default::default()
+        },
+    ))
+    .with_children(|parent| {
+        parent.spawn((TextSpan::default(), font.clone(), TextColor(COLOR_VALUE), HudText::Lives));
+        parent.spawn((TextSpan::new("  ROUND "), font.clone(), TextColor(COLOR_LABEL)));
+        parent.spawn((TextSpan::default(), font.clone(), TextColor(COLOR_VALUE), HudText::Round));
+    });
+}
+
+/// 着地した行がこれまでより先なら得点を知らせる関数
+fn score_step(
+    mut land_events: EventReader<LandEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut furthest_row: ResMut<FurthestRow>,
+) {
+    info_once!("score_step");
+
+    for row in land_events.read() {
+        if **row > **furthest_row {
+            **furthest_row = **row;
+            score_events.send(ScoreEvent::Step);
+        }
+    }
+}
+
+/// ミスまたはゴールしたら、到達した行をスタート地点に戻す関数
+fn score_reset_row(
+    mut miss_events: EventReader<MissEvent>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut furthest_row: ResMut<FurthestRow>,
+    level: Res<Level>,
+) {
+    info_once!("score_reset_row");
+
+    if miss_events.is_empty() && goal_events.is_empty() {
+        return;
+    }
+    miss_events.clear();
+    goal_events.clear();
+
+    *furthest_row = FurthestRow::start(&level);
+}
+
+/// 得点を加算し、最高得点を更新する関数
+fn score_add(
+    mut events: EventReader<ScoreEvent>,
+    mut score: ResMut<Score>,
+    mut high_score: ResMut<HighScore>,
+) {
+    info_once!("score_add");
+
+    for event in events.read() {
+        **score += points(event);
+        debug!("score: {:?} -> {}", event, **score);
+    }
+    if **score > **high_score {
+        **high_score = **score;
+    }
+}
+
+/// ゲームを始めるときに得点を0に戻す関数
+fn score_reset(
+    mut score: ResMut<Score>,
+    mut furthest_row: ResMut<FurthestRow>,
+    level: Res<Level>,
+) {
+    info_once!("score_reset");
+
+    **score = 0;
+    *furthest_row = FurthestRow::start(&level);
+}
+
+/// HUDの表示を更新する関数
+fn score_hud(
+    mut query: Query<(&mut TextSpan, &HudText)>,
+    score: Res<Score>,
+    high_score: Res<HighScore>,
+    lives: Res<Lives>,
+    round: Res<Round>,
+) {
+    info_once!("score_hud");
+
+    for (mut span, hud_text) in &mut query {
+        **span = match hud_text {
+            HudText::Score => format!("{:05}", **score),
+            HudText::HighScore => format!("{:05}", **high_score),
+            HudText::Lives => lives.to_string(),
+            HudText::Round => round.to_string(),
+        };
+    }
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Score>()
+            .init_resource::<HighScore>()
+            .init_resource::<FurthestRow>()
+            .add_systems(Startup, score_setup)
+            .add_systems(OnEnter(InGame), score_reset)
+            // ステートが変わる直前に送られたイベントも取りこぼさないよう、ステージを読み込んだ後は常に実行する
+            // 着地とミスの順番で結果が変わるため、行の記録は固定タイムステップで処理する
+            .add_systems(FixedPostUpdate, (
+                score_step,
+                score_reset_row,
+            ).chain().run_if(resource_exists::<Level>))
+            .add_systems(Update, (
+                score_add,
+                score_hud,
+            ).chain().in_set(GameplaySet::Ui))
+        ;
+    }
+}
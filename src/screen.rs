@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+
+use crate::{Action, ActionEvent, AppState, GameOverEvent, RoundClearEvent, GAMETITLE};
+
+const TITLE_FONT_SIZE: f32 = 48.0;
+const MESSAGE_FONT_SIZE: f32 = 32.0;
+const HINT_FONT_SIZE: f32 = 20.0;
+const HINT_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+const HINT_CONFIRM: &str = "Press Enter / Start ...";
+const HINT_OPTIONS: &str = "O / Select : Options";
+/// ラウンドクリアを表示している時間（秒）
+const ROUND_CLEAR_SECS: f32 = 2.0;
+
+/// ラウンドクリアの表示を終えるまでのタイマーを管理するリソース
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct RoundClearTimer(Timer);
+
+/// 画面中央にメッセージと、その下に操作の案内を表示する
+fn spawn_message(commands: &mut Commands, state: AppState, message: &str, font_size: f32, hints: &[&str]) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..Default::default()
+            },
+            StateScoped(state),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(message),
+                TextFont::from_font_size(font_size),
+            ));
+            for hint in hints {
+                parent.spawn((
+                    Text::new(*hint),
+                    TextFont::from_font_size(HINT_FONT_SIZE),
+                    TextColor(HINT_COLOR),
+                ));
+            }
+        });
+}
+
+/// タイトル画面を表示する関数
+fn screen_title_setup(mut commands: Commands) {
+    info_once!("screen_title_setup");
+
+    spawn_message(&mut commands, AppState::Title, GAMETITLE, TITLE_FONT_SIZE, &[HINT_CONFIRM, HINT_OPTIONS]);
+}
+
+/// 一時停止画面を表示する関数
+fn screen_paused_setup(mut commands: Commands) {
+    info_once!("screen_paused_setup");
+
+    spawn_message(&mut commands, AppState::Paused, "PAUSE", MESSAGE_FONT_SIZE, &[]);
+}
+
+/// ラウンドクリア画面を表示する関数
+fn screen_round_clear_setup(mut commands: Commands) {
+    info_once!("screen_round_clear_setup");
+
+    spawn_message(&mut commands, AppState::RoundClear, "ROUND CLEAR", MESSAGE_FONT_SIZE, &[]);
+    commands.insert_resource(RoundClearTimer(Timer::from_seconds(ROUND_CLEAR_SECS, TimerMode::Once)));
+}
+
+/// ゲームオーバー画面を表示する関数
+fn screen_game_over_setup(mut commands: Commands) {
+    info_once!("screen_game_over_setup");
+
+    spawn_message(&mut commands, AppState::GameOver, "GAME OVER", MESSAGE_FONT_SIZE, &[HINT_CONFIRM]);
+}
+
+/// 決定の操作が入力されたら、タイトル画面ならゲームを始め、ゲームオーバー画面ならタイトル画面に戻す関数
+pub fn screen_confirm(
+    mut events: EventReader<ActionEvent>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    info_once!("screen_confirm");
+
+    if !events.read().any(|event| **event == Action::Confirm) {
+        return;
+    }
+
+    match state.get() {
+        AppState::Title => next_state.set(AppState::Playing),
+        AppState::GameOver => next_state.set(AppState::Title),
+        _ => {}
+    }
+}
+
+/// タイトル画面でオプション画面を開く操作が入力されたらオプション画面に進める関数
+fn screen_title_options(
+    mut events: EventReader<ActionEvent>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    info_once!("screen_title_options");
+
+    if events.read().any(|event| **event == Action::Options) && *state.get() == AppState::Title {
+        next_state.set(AppState::Options);
+    }
+}
+
+/// ラウンドクリアの表示が終わったらゲームを再開する関数
+fn screen_round_clear(
+    mut timer: ResMut<RoundClearTimer>,
+    mut next_state: ResMut<NextState<AppState>>,
+    time: Res<Time>,
+) {
+    info_once!("screen_round_clear");
+
+    if timer.tick(time.delta()).just_finished() {
+        next_state.set(AppState::Playing);
+    }
+}
+
+/// ラウンドクリアのタイマーを片付ける関数
+fn screen_round_clear_teardown(mut commands: Commands) {
+    info_once!("screen_round_clear_teardown");
+
+    commands.remove_resource::<RoundClearTimer>();
+}
+
+/// ゲーム中のイベントを受け取り、ステートを切り替える関数
+fn screen_transition(
+    mut round_clear_events: EventReader<RoundClearEvent>,
+    mut game_over_events: EventReader<GameOverEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    info_once!("screen_transition");
+
+    if !game_over_events.is_empty() {
+        game_over_events.clear();
+        next_state.set(AppState::GameOver);
+    } else if !round_clear_events.is_empty() {
+        round_clear_events.clear();
+        next_state.set(AppState::RoundClear);
+    }
+}
+
+pub struct ScreenPlugin;
+
+impl Plugin for ScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Title), screen_title_setup)
+            .add_systems(OnEnter(AppState::Paused), screen_paused_setup)
+            .add_systems(OnEnter(AppState::RoundClear), screen_round_clear_setup)
+            .add_systems(OnEnter(AppState::GameOver), screen_game_over_setup)
+            .add_systems(OnExit(AppState::RoundClear), screen_round_clear_teardown)
+            .add_systems(Update, (
+                // ゲーム中のタップなど、決定に使わない画面で届いた操作もここで読み捨てる
+                screen_confirm,
+                screen_title_options,
+                screen_round_clear.run_if(in_state(AppState::RoundClear)),
+            ))
+            // 切り替えを待っている間に、同じフレームの残りの固定タイムステップでゲームが進まないようにする
+            .add_systems(FixedPostUpdate, screen_transition.run_if(in_state(AppState::Playing)))
+        ;
+    }
+}
@@ -0,0 +1,77 @@
+//! 各モジュールのテストで共通に使う、画面なしのアプリを作る関数
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+};
+
+use crate::{
+    GamePlugin, PATH_LEVEL,
+    key::KeyBindingsPath,
+    level::{Level, LevelHandle},
+    replay::ReplayPath,
+};
+
+/// 固定タイムステップの長さ。ゲームと同じにする
+const FIXED_STEP_SECS: f64 = 1.0 / 60.0;
+
+/// システムを試すための最小限のアプリを作る
+/// 時間は自動では進まないので、必要なテストで`Time`を進める
+pub fn app() -> App {
+    let mut app = App::new();
+    app
+        .add_plugins(StatesPlugin)
+        .insert_resource(Time::<()>::default());
+    app
+}
+
+/// 送られたイベントをすべて取り出す
+pub fn drain<E: Event>(app: &mut App) -> Vec<E> {
+    app.world_mut().resource_mut::<Events<E>>().drain().collect()
+}
+
+/// テストごとに使う一時ディレクトリを作る。前回の中身は消す
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ittoku-frogger-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// ゲーム全体を画面なしで動かすアプリを作り、ステージを読み込んだ状態にする
+/// - 1回の更新で固定タイムステップがちょうど1回進むよう、時間を手動で進める
+/// - 設定とリプレイは`dir`に読み書きし、ユーザーのファイルには触れない
+pub fn game_app(dir: &Path) -> App {
+    let mut app = App::new();
+    app
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StatesPlugin,
+            bevy::input::InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+        ))
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(FIXED_STEP_SECS)))
+        .insert_resource(KeyBindingsPath(Some(dir.join("keybindings.ron"))))
+        .insert_resource(ReplayPath(Some(dir.join("last.ron"))))
+        .add_plugins(GamePlugin);
+
+    // アセットの読み込みは別のスレッドで進み、終わるまでのフレーム数が決まらないため、
+    // ステージのファイルを直接読んで読み込み済みのハンドルに差し替える
+    app.update();
+    let text = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(PATH_LEVEL)).unwrap();
+    let level: Level = ron::from_str(&text).unwrap();
+    let handle = app.world_mut().resource_mut::<Assets<Level>>().add(level);
+    app.insert_resource(LevelHandle(handle));
+    app
+}
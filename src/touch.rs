@@ -0,0 +1,414 @@
+use bevy::{
+    input::touch::TouchPhase,
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{
+    Action, ActionEvent, AppState, InGame,
+    key::ActionSystem,
+};
+
+/// スワイプとみなす最小の距離（論理ピクセル）
+const SWIPE_MIN_DISTANCE: f32 = 32.0;
+/// スワイプとみなす最大の時間（秒）
+const SWIPE_MAX_SECS: f32 = 0.5;
+/// タップとみなす最大の距離（論理ピクセル）
+const TAP_MAX_DISTANCE: f32 = 8.0;
+const DPAD_BUTTON_SIZE: f32 = 44.0;
+const DPAD_MARGIN: f32 = 16.0;
+const DPAD_FONT_SIZE: f32 = 24.0;
+const COLOR_DPAD: Color = Color::srgba(1.0, 1.0, 1.0, 0.2);
+
+/// タッチ操作の設定を管理するリソース
+/// - swipe_min_distance: スワイプとみなす最小の距離
+/// - swipe_max_secs: スワイプとみなす最大の時間
+/// - tap_max_distance: タップとみなす最大の距離
+/// - show_dpad: 画面上に十字ボタンを表示するか
+#[derive(Resource, Debug)]
+pub struct TouchControls {
+    pub swipe_min_distance: f32,
+    pub swipe_max_secs: f32,
+    pub tap_max_distance: f32,
+    pub show_dpad: bool,
+}
+
+impl Default for TouchControls {
+    fn default() -> Self {
+        Self {
+            swipe_min_distance: SWIPE_MIN_DISTANCE,
+            swipe_max_secs: SWIPE_MAX_SECS,
+            tap_max_distance: TAP_MAX_DISTANCE,
+            // ブラウザで遊ぶときはスマートフォンを想定して表示する
+            show_dpad: cfg!(target_arch = "wasm32"),
+        }
+    }
+}
+
+/// 触れ始めた位置と時刻
+#[derive(Debug, Clone, Copy)]
+struct TouchStart {
+    position: Vec2,
+    secs: f32,
+}
+
+/// 複数の指でのタップを調べるための、指を置いている間の状態
+/// - fingers: 同時に置いた指の最大の数
+/// - moved: タップとみなせない動きをした指があったか
+#[derive(Debug, Default)]
+struct MultiTap {
+    fingers: usize,
+    moved: bool,
+}
+
+/// 画面上の十字ボタンのコンポーネント
+/// - 0: 押したときの操作
+#[derive(Component, Debug)]
+struct DpadButton(Action);
+
+/// ドラッグした向きを移動の操作に変換する
+/// 画面の座標は下向きが正のため、上下を反転させる
+fn swipe_action(delta: Vec2) -> Action {
+    if delta.x.abs() > delta.y.abs() {
+        if delta.x < 0.0 { Action::MoveLeft } else { Action::MoveRight }
+    } else if delta.y < 0.0 {
+        Action::MoveUp
+    } else {
+        Action::MoveDown
+    }
+}
+
+/// タップを操作に変換する
+/// 両方を送ると、キー割り当て画面でカーソルが動いてから決定されてしまうため、画面で分ける
+/// 一時停止中は決定の操作を受け付けないので、再開の操作にする
+fn tap_action(state: &AppState) -> Action {
+    match state {
+        AppState::Playing => Action::MoveUp,
+        AppState::Paused => Action::Pause,
+        _ => Action::Confirm,
+    }
+}
+
+/// 画面上の十字ボタンを配置する関数
+fn touch_dpad_setup(
+    mut commands: Commands,
+    touch_controls: Res<TouchControls>,
+) {
+    info_once!("touch_dpad_setup");
+
+    if !touch_controls.show_dpad {
+        return;
+    }
+
+    let buttons = [
+        (Action::MoveUp, "^", 1, 0),
+        (Action::MoveLeft, "<", 0, 1),
+        (Action::MoveRight, ">", 2, 1),
+        (Action::MoveDown, "v", 1, 2),
+    ];
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(DPAD_MARGIN),
+                bottom: Val::Px(DPAD_MARGIN + DPAD_BUTTON_SIZE),
+                width: Val::Px(DPAD_BUTTON_SIZE * 3.0),
+                height: Val::Px(DPAD_BUTTON_SIZE * 3.0),
+                ..Default::default()
+            },
+            StateScoped(InGame),
+        ))
+        .with_children(|parent| {
+            for (action, label, x, y) in buttons {
+                parent.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(DPAD_BUTTON_SIZE * x as f32),
+                        top: Val::Px(DPAD_BUTTON_SIZE * y as f32),
+                        width: Val::Px(DPAD_BUTTON_SIZE),
+                        height: Val::Px(DPAD_BUTTON_SIZE),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(COLOR_DPAD),
+                    DpadButton(action),
+                ))
+                .with_child((
+                    Text::new(label),
+                    TextFont::from_font_size(DPAD_FONT_SIZE),
+                ));
+            }
+        });
+}
+
+/// タッチの入力をスワイプ、タップ、十字ボタンの操作に変換する関数
+/// - スワイプ: 指を動かした向きに跳ぶ
+/// - タップ: ゲーム中は前に跳ぶ。タイトル画面などでは決定の操作になる
+/// - 2本指のタップ: Escキーと同じく、一時停止と戻るの操作になる
+/// - 十字ボタン: 触れた時点でボタンの向きに跳ぶ
+#[allow(clippy::too_many_arguments)]
+fn touch_actions(
+    mut events: EventWriter<ActionEvent>,
+    mut touch_events: EventReader<TouchInput>,
+    mut starts: Local<HashMap<u64, TouchStart>>,
+    mut multi_tap: Local<MultiTap>,
+    dpad_query: Query<(&ComputedNode, &GlobalTransform, &DpadButton)>,
+    touch_controls: Res<TouchControls>,
+    state: Res<State<AppState>>,
+    time: Res<Time>,
+) {
+    info_once!("touch_actions");
+
+    for touch in touch_events.read() {
+        match touch.phase {
+            TouchPhase::Started => {
+                // 十字ボタンに触れたときはジェスチャーとして扱わない
+                let pressed = dpad_query.iter().find(|(node, transform, _)| {
+                    let scale = node.inverse_scale_factor();
+                    Rect::from_center_size(transform.translation().truncate() * scale, node.size() * scale)
+                        .contains(touch.position)
+                });
+                if let Some((_, _, button)) = pressed {
+                    events.send(ActionEvent(button.0));
+                    continue;
+                }
+                starts.insert(touch.id, TouchStart { position: touch.position, secs: time.elapsed_secs() });
+                multi_tap.fingers = multi_tap.fingers.max(starts.len());
+            }
+            TouchPhase::Ended => {
+                let Some(start) = starts.remove(&touch.id) else {
+                    continue;
+                };
+                let quick = time.elapsed_secs() - start.secs <= touch_controls.swipe_max_secs;
+                let delta = touch.position - start.position;
+                let distance = delta.length();
+
+                // 複数の指を置いたときは、すべての指を離してからまとめて判定する
+                if multi_tap.fingers >= 2 {
+                    multi_tap.moved |= !quick || distance > touch_controls.tap_max_distance;
+                    if starts.is_empty() {
+                        if !multi_tap.moved {
+                            events.send(ActionEvent(Action::Pause));
+                            events.send(ActionEvent(Action::Back));
+                        }
+                        *multi_tap = MultiTap::default();
+                    }
+                    continue;
+                }
+                *multi_tap = MultiTap::default();
+
+                if !quick {
+                    continue;
+                }
+                if distance >= touch_controls.swipe_min_distance {
+                    events.send(ActionEvent(swipe_action(delta)));
+                } else if distance <= touch_controls.tap_max_distance {
+                    events.send(ActionEvent(tap_action(state.get())));
+                }
+            }
+            TouchPhase::Canceled => {
+                starts.remove(&touch.id);
+                multi_tap.moved = true;
+                if starts.is_empty() {
+                    *multi_tap = MultiTap::default();
+                }
+            }
+            TouchPhase::Moved => {}
+        }
+    }
+}
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TouchControls>()
+            .add_systems(OnEnter(InGame), touch_dpad_setup)
+            .add_systems(PreUpdate, touch_actions.in_set(ActionSystem))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        key::{KeyBindings, key_pause},
+        options::{OptionsCursor, options_input},
+        screen::screen_confirm,
+        testing,
+    };
+
+    const FINGER: u64 = 0;
+    const SECOND_FINGER: u64 = 1;
+    const POSITION: Vec2 = Vec2::new(100.0, 100.0);
+
+    /// タッチの操作を受け取って画面を切り替えるシステムも動かす
+    fn app(state: AppState) -> App {
+        let mut app = testing::app();
+        app
+            .insert_state(state)
+            .add_event::<TouchInput>()
+            .add_event::<ActionEvent>()
+            .init_resource::<TouchControls>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<OptionsCursor>()
+            .add_systems(Update, (
+                touch_actions,
+                (
+                    screen_confirm,
+                    key_pause,
+                    options_input.run_if(in_state(AppState::Options)),
+                ),
+            ).chain());
+        app
+    }
+
+    fn touch(app: &mut App, phase: TouchPhase, position: Vec2, id: u64) {
+        app.world_mut().send_event(TouchInput { phase, position, window: Entity::PLACEHOLDER, force: None, id });
+    }
+
+    /// 指を置いてから離すまでを送り、変換された操作を返す
+    fn gesture(app: &mut App, from: Vec2, to: Vec2, secs: f32) -> Vec<Action> {
+        touch(app, TouchPhase::Started, from, FINGER);
+        app.update();
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs_f32(secs));
+        touch(app, TouchPhase::Ended, to, FINGER);
+        app.update();
+
+        testing::drain::<ActionEvent>(app).into_iter().map(|event| *event).collect()
+    }
+
+    fn tap(app: &mut App) -> Vec<Action> {
+        gesture(app, POSITION, POSITION, 0.1)
+    }
+
+    /// 2本の指を置いてから、1本ずつ離す
+    fn two_finger_tap(app: &mut App) -> Vec<Action> {
+        touch(app, TouchPhase::Started, POSITION, FINGER);
+        touch(app, TouchPhase::Started, POSITION + Vec2::X * 50.0, SECOND_FINGER);
+        app.update();
+        touch(app, TouchPhase::Ended, POSITION, FINGER);
+        app.update();
+        touch(app, TouchPhase::Ended, POSITION + Vec2::X * 50.0, SECOND_FINGER);
+        app.update();
+
+        testing::drain::<ActionEvent>(app).into_iter().map(|event| *event).collect()
+    }
+
+    /// 次の更新で切り替わったステートを返す
+    fn state_after_update(app: &mut App) -> AppState {
+        app.update();
+        *app.world().resource::<State<AppState>>().get()
+    }
+
+    #[test]
+    fn swipe_action_uses_dominant_axis() {
+        assert_eq!(swipe_action(Vec2::new(-40.0, 10.0)), Action::MoveLeft);
+        assert_eq!(swipe_action(Vec2::new(40.0, -10.0)), Action::MoveRight);
+        // 画面の座標は下向きが正
+        assert_eq!(swipe_action(Vec2::new(10.0, -40.0)), Action::MoveUp);
+        assert_eq!(swipe_action(Vec2::new(-10.0, 40.0)), Action::MoveDown);
+    }
+
+    #[test]
+    fn swipe_sends_move() {
+        let mut app = app(AppState::Playing);
+        assert_eq!(gesture(&mut app, POSITION, Vec2::new(160.0, 110.0), 0.1), vec![Action::MoveRight]);
+        assert_eq!(gesture(&mut app, POSITION, Vec2::new(100.0, 40.0), 0.1), vec![Action::MoveUp]);
+    }
+
+    #[test]
+    fn slow_swipe_is_ignored() {
+        let mut app = app(AppState::Playing);
+        assert!(gesture(&mut app, POSITION, Vec2::new(160.0, 100.0), SWIPE_MAX_SECS + 0.1).is_empty());
+    }
+
+    #[test]
+    fn short_drag_is_neither_tap_nor_swipe() {
+        let mut app = app(AppState::Playing);
+        let distance = (TAP_MAX_DISTANCE + SWIPE_MIN_DISTANCE) / 2.0;
+        assert!(gesture(&mut app, POSITION, POSITION + Vec2::X * distance, 0.1).is_empty());
+    }
+
+    #[test]
+    fn tap_hops_while_playing() {
+        let mut app = app(AppState::Playing);
+        assert_eq!(gesture(&mut app, POSITION, Vec2::new(102.0, 101.0), 0.1), vec![Action::MoveUp]);
+        assert_eq!(state_after_update(&mut app), AppState::Playing);
+    }
+
+    #[test]
+    fn tap_starts_game_from_title() {
+        let mut app = app(AppState::Title);
+        assert_eq!(tap(&mut app), vec![Action::Confirm]);
+        assert_eq!(state_after_update(&mut app), AppState::Playing);
+    }
+
+    #[test]
+    fn tap_leaves_game_over() {
+        let mut app = app(AppState::GameOver);
+        assert_eq!(tap(&mut app), vec![Action::Confirm]);
+        assert_eq!(state_after_update(&mut app), AppState::Title);
+    }
+
+    #[test]
+    fn tap_resumes_from_pause() {
+        let mut app = app(AppState::Paused);
+        assert_eq!(tap(&mut app), vec![Action::Pause]);
+        assert_eq!(state_after_update(&mut app), AppState::Playing);
+    }
+
+    #[test]
+    fn two_finger_tap_pauses_and_resumes() {
+        let mut app = app(AppState::Playing);
+        assert_eq!(two_finger_tap(&mut app), vec![Action::Pause, Action::Back]);
+        assert_eq!(state_after_update(&mut app), AppState::Paused);
+
+        two_finger_tap(&mut app);
+        assert_eq!(state_after_update(&mut app), AppState::Playing);
+    }
+
+    #[test]
+    fn two_finger_tap_leaves_options() {
+        let mut app = app(AppState::Options);
+
+        // タップでキー待ちになっても、2本指のタップで取り消せる
+        tap(&mut app);
+        two_finger_tap(&mut app);
+        assert_eq!(state_after_update(&mut app), AppState::Options);
+
+        two_finger_tap(&mut app);
+        assert_eq!(state_after_update(&mut app), AppState::Title);
+    }
+
+    #[test]
+    fn two_finger_swipe_sends_nothing() {
+        let mut app = app(AppState::Playing);
+        touch(&mut app, TouchPhase::Started, POSITION, FINGER);
+        touch(&mut app, TouchPhase::Started, POSITION + Vec2::X * 50.0, SECOND_FINGER);
+        app.update();
+        touch(&mut app, TouchPhase::Ended, POSITION + Vec2::Y * 60.0, FINGER);
+        touch(&mut app, TouchPhase::Ended, POSITION + Vec2::X * 50.0, SECOND_FINGER);
+        app.update();
+
+        assert!(testing::drain::<ActionEvent>(&mut app).is_empty());
+    }
+
+    #[test]
+    fn canceled_touch_sends_nothing() {
+        let mut app = app(AppState::Playing);
+        touch(&mut app, TouchPhase::Started, POSITION, FINGER);
+        touch(&mut app, TouchPhase::Canceled, POSITION, FINGER);
+        touch(&mut app, TouchPhase::Ended, POSITION, FINGER);
+        app.update();
+
+        assert!(testing::drain::<ActionEvent>(&mut app).is_empty());
+    }
+}
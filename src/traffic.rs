@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    AppState, GameplaySet, InGame, MissEvent, Velocity, WINDOW_SIZE, apply_velocity, in_play,
+    collision::{CollisionEvent, Hitbox, HitboxKind, collision_broadphase},
+    difficulty::Difficulty,
+    grid::{Grid, GridPos},
+    interpolation::Interpolated,
+    level::{Level, LevelReloadEvent, RowKind},
+};
+
+/// 画面外で車両が折り返すまでの余白
+const WRAP_MARGIN: f32 = 64.0;
+const COLOR_CAR: Color = Color::srgb(0.9, 0.8, 0.2);
+const COLOR_TRUCK: Color = Color::srgb(0.9, 0.3, 0.3);
+
+/// 車線を走る車両の種類
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Vehicle {
+    Car,
+    Truck,
+}
+
+impl Vehicle {
+    fn size(&self) -> Vec2 {
+        match self {
+            Vehicle::Car => Vec2::new(28.0, 24.0),
+            Vehicle::Truck => Vec2::new(60.0, 24.0),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Vehicle::Car => COLOR_CAR,
+            Vehicle::Truck => COLOR_TRUCK,
+        }
+    }
+}
+
+/// 車線を走る車両のコンポーネント
+#[derive(Component, Debug)]
+struct Obstacle;
+
+/// ステージの道路の行に、難易度に合わせて車両を配置する
+fn spawn_traffic(commands: &mut Commands, grid: &Grid, level: &Level, difficulty: &Difficulty) {
+    let span = WINDOW_SIZE.x + WRAP_MARGIN * 2.0;
+
+    for (row, kind) in level.iter_rows() {
+        let RowKind::Road { lane, vehicle } = kind else {
+            continue;
+        };
+        let lane = difficulty.road_lane(lane);
+        let size = vehicle.size();
+        let (count, spacing) = lane.layout(span);
+
+        for i in 0..count {
+            let x = -span / 2.0 + spacing * i as f32;
+            let position = Vec2::new(x, grid.to_world(GridPos::new(0, row)).y);
+            commands.spawn((
+                Sprite::from_color(vehicle.color(), size),
+                Transform::from_translation(position.extend(0.0)),
+                Interpolated::new(position.extend(0.0)),
+                grid.to_grid(position),
+                Obstacle,
+                Hitbox::new(HitboxKind::Vehicle, size),
+                Velocity(lane.velocity()),
+                StateScoped(InGame),
+            ));
+        }
+    }
+}
+
+/// 配置してある車両を消し、今のステージと難易度で配置し直す
+fn respawn_traffic(
+    commands: &mut Commands,
+    query: &Query<Entity, With<Obstacle>>,
+    grid: &Grid,
+    level: &Level,
+    difficulty: &Difficulty,
+) {
+    for entity in query {
+        commands.entity(entity).despawn();
+    }
+    spawn_traffic(commands, grid, level, difficulty);
+}
+
+/// 車両を配置する関数
+fn traffic_setup(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("traffic_setup");
+
+    spawn_traffic(&mut commands, &grid, &level, &difficulty);
+}
+
+/// ステージを読み込み直したら車両を配置し直す関数
+fn traffic_reload(
+    mut commands: Commands,
+    mut events: EventReader<LevelReloadEvent>,
+    query: Query<Entity, With<Obstacle>>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("traffic_reload");
+
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    respawn_traffic(&mut commands, &query, &grid, &level, &difficulty);
+}
+
+/// 次のラウンドを始めるときに、新しい難易度で車両を配置し直す関数
+fn traffic_next_round(
+    mut commands: Commands,
+    query: Query<Entity, With<Obstacle>>,
+    grid: Res<Grid>,
+    level: Res<Level>,
+    difficulty: Res<Difficulty>,
+) {
+    info_once!("traffic_next_round");
+
+    respawn_traffic(&mut commands, &query, &grid, &level, &difficulty);
+}
+
+/// 画面外に出た車両を反対側に戻す関数
+fn traffic_wrap(mut query: Query<&mut Transform, With<Obstacle>>) {
+    info_once!("traffic_wrap");
+
+    let span = WINDOW_SIZE.x + WRAP_MARGIN * 2.0;
+
+    for mut transform in &mut query {
+        let x = transform.translation.x;
+
+        if x > span / 2.0 {
+            transform.translation.x -= span;
+        } else if x < -span / 2.0 {
+            transform.translation.x += span;
+        }
+    }
+}
+
+/// プレイヤーが車両に当たったらミスとする関数
+fn traffic_collision(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut events: EventWriter<MissEvent>,
+) {
+    info_once!("traffic_collision");
+
+    let hit = collision_events
+        .read()
+        .any(|event| matches!(event, CollisionEvent::PlayerHitVehicle(_)));
+    if hit {
+        events.send_default();
+    }
+}
+
+pub struct TrafficPlugin;
+
+impl Plugin for TrafficPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(InGame), traffic_setup)
+            .add_systems(OnExit(AppState::RoundClear), traffic_next_round)
+            .add_systems(Update, traffic_reload.run_if(in_state(InGame)))
+            .add_systems(FixedUpdate, (
+                traffic_wrap.in_set(GameplaySet::Physics).after(apply_velocity),
+                traffic_collision.in_set(GameplaySet::Collision).after(collision_broadphase),
+            ).run_if(in_play))
+        ;
+    }
+}